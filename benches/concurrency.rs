@@ -0,0 +1,150 @@
+// benches/concurrency.rs
+//
+// 并发吞吐量基准：多个线程同时通过同一个 ApiMgr 执行 GET 和 PUT，
+// 与旧的做法 (整个 ApiMgr 放在一把全局 Mutex 中) 对比。
+// 带 "+wal" 的场景使用数据目录，每次提交都要 fsync 预写日志；
+// 共享 ApiMgr 时 GET 不必等待正在 fsync 的提交，全局 Mutex 则会让所有请求排队。
+//
+// 读取路径不打印日志 (否则 GET 的吞吐量主要取决于 stdout 的锁)，写入路径仍然把日志打印到 stdout，
+// 结果打印到 stderr，因此运行时丢弃 stdout：
+//     cargo bench --bench concurrency > /dev/null
+// SKDB_BENCH_SECS 设置每个场景运行的秒数 (默认 1)。
+
+use skdb::api::ApiMgr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PRELOADED_KEYS: u64 = 10_000;
+const THREAD_COUNTS: [usize; 4] = [1, 2, 4, 8];
+
+// 被测的共享方式：直接共享 ApiMgr，或者像之前的服务器那样放在全局 Mutex 中
+trait Shared: Send + Sync {
+    fn with<T>(&self, op: impl FnOnce(&ApiMgr) -> T) -> T;
+}
+
+impl Shared for ApiMgr {
+    fn with<T>(&self, op: impl FnOnce(&ApiMgr) -> T) -> T {
+        op(self)
+    }
+}
+
+impl Shared for Mutex<ApiMgr> {
+    fn with<T>(&self, op: impl FnOnce(&ApiMgr) -> T) -> T {
+        op(&self.lock().unwrap())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Workload {
+    Get,
+    Put,
+    Mixed, // 90% GET，10% PUT
+}
+
+impl Workload {
+    fn name(self) -> &'static str {
+        match self {
+            Workload::Get => "get",
+            Workload::Put => "put",
+            Workload::Mixed => "mixed 90/10",
+        }
+    }
+}
+
+// xorshift64，足够用来挑选键，不需要额外的依赖
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// durable 时在临时目录中打开 (返回该目录以便之后删除)，否则使用纯内存的 ApiMgr
+fn preloaded_api_mgr(durable: bool) -> (ApiMgr, Option<PathBuf>) {
+    static NEXT_DIR: AtomicU64 = AtomicU64::new(0);
+    let (api_mgr, data_dir) = if durable {
+        let name = format!("skdb_bench_{}_{}", std::process::id(), NEXT_DIR.fetch_add(1, Ordering::Relaxed));
+        let data_dir = std::env::temp_dir().join(name);
+        (ApiMgr::open(&data_dir).unwrap(), Some(data_dir))
+    } else {
+        (ApiMgr::new(), None)
+    };
+    let transaction_id = api_mgr.begin_transaction().unwrap();
+    for i in 0..PRELOADED_KEYS {
+        api_mgr.put(transaction_id, format!("key{}", i), format!("value{}", i)).unwrap();
+    }
+    api_mgr.commit_transaction(transaction_id).unwrap();
+    (api_mgr, data_dir)
+}
+
+fn put(shared: &impl Shared, key: String, value: String) {
+    shared.with(|api_mgr| {
+        let transaction_id = api_mgr.begin_transaction().unwrap();
+        api_mgr.put(transaction_id, key, value).unwrap();
+        // 每个线程只写自己的键，不会有冲突
+        api_mgr.commit_transaction(transaction_id).unwrap();
+    });
+}
+
+// 在 threads 个线程中运行 duration 时间，返回每秒完成的操作数
+fn run<S: Shared + 'static>(shared: Arc<S>, workload: Workload, threads: usize, duration: Duration) -> f64 {
+    let stop = Arc::new(AtomicBool::new(false));
+    let completed = Arc::new(AtomicU64::new(0));
+    let handles: Vec<_> = (0..threads)
+        .map(|thread_index| {
+            let (shared, stop, completed) = (Arc::clone(&shared), Arc::clone(&stop), Arc::clone(&completed));
+            thread::spawn(move || {
+                let mut random = 0x9E37_79B9_7F4A_7C15 ^ (thread_index as u64 + 1);
+                let mut operations = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let n = next_random(&mut random);
+                    let is_put = match workload {
+                        Workload::Get => false,
+                        Workload::Put => true,
+                        Workload::Mixed => n.is_multiple_of(10),
+                    };
+                    if is_put {
+                        put(shared.as_ref(), format!("thread{}/{}", thread_index, n % 1000), n.to_string());
+                    } else {
+                        let key = format!("key{}", n % PRELOADED_KEYS);
+                        std::hint::black_box(shared.with(|api_mgr| api_mgr.get(key)));
+                    }
+                    operations += 1;
+                }
+                completed.fetch_add(operations, Ordering::Relaxed);
+            })
+        })
+        .collect();
+
+    let started = Instant::now();
+    thread::sleep(duration);
+    stop.store(true, Ordering::Relaxed);
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    completed.load(Ordering::Relaxed) as f64 / started.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let seconds = std::env::var("SKDB_BENCH_SECS").ok().and_then(|value| value.parse().ok()).unwrap_or(1);
+    let duration = Duration::from_secs(seconds);
+
+    eprintln!("{:<16} {:>8} {:>16} {:>16}", "workload", "threads", "shared ops/s", "mutex ops/s");
+    let scenarios = [(Workload::Get, false), (Workload::Put, false), (Workload::Mixed, false), (Workload::Mixed, true)];
+    for (workload, durable) in scenarios {
+        let name = format!("{}{}", workload.name(), if durable { " +wal" } else { "" });
+        for threads in THREAD_COUNTS {
+            let (api_mgr, shared_dir) = preloaded_api_mgr(durable);
+            let shared = run(Arc::new(api_mgr), workload, threads, duration);
+            let (api_mgr, mutex_dir) = preloaded_api_mgr(durable);
+            let mutex = run(Arc::new(Mutex::new(api_mgr)), workload, threads, duration);
+            eprintln!("{:<16} {:>8} {:>16.0} {:>16.0}", name, threads, shared, mutex);
+            for data_dir in [shared_dir, mutex_dir].into_iter().flatten() {
+                let _ = std::fs::remove_dir_all(data_dir);
+            }
+        }
+    }
+}
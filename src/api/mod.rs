@@ -1,15 +1,81 @@
 // src/api/mod.rs
 
 // API 模块 (APIMgr)
-use crate::transaction::TxnMgr; // 引入事务管理器
+use crate::error::Error;
+use crate::storage::{GcStats, KeyRange, RetentionPolicy, Version};
+use crate::transaction::{BeginOptions, ChangeEvent, GcMetrics, SnapshotInfo, TxnMgr}; // 引入事务管理器
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+// 单个批量写入最多包含的操作数
+pub const MAX_BATCH_OPERATIONS: usize = 10_000;
+
+// 批量写入中的一个操作
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOp {
+    Put {
+        key: String,
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ttl_seconds: Option<u64>, // 可选的过期时间 (秒)
+    },
+    Delete { key: String },
+}
+
+impl BatchOp {
+    pub fn key(&self) -> &str {
+        match self {
+            BatchOp::Put { key, .. } | BatchOp::Delete { key } => key,
+        }
+    }
+}
+
+// 批量写入中每个操作的结果 (与请求中的操作一一对应)
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BatchOpResult {
+    pub key: String,
+    pub existed: bool, // 操作之前该键在事务中是否存在
+}
+
+// 在调用者的事务中执行批量写入时使用的内部保存点；同名保存点以最近创建的为准，不会影响调用者自己的保存点
+const BATCH_SAVEPOINT: &str = "__put_many";
+
+// 依次执行批量写入中的操作。existed 不记录到读集中：批量写入是盲写，结果不依赖之前的值
+fn apply_batch(txn_mgr: &TxnMgr, transaction_id: u64, ops: Vec<BatchOp>) -> Result<Vec<BatchOpResult>, Error> {
+    let mut results = Vec::with_capacity(ops.len());
+    for (index, op) in ops.into_iter().enumerate() {
+        let key = op.key().to_string();
+        if key.is_empty() {
+            return Err(Error::InvalidArgument(format!("operation {} in the batch has an empty key", index)));
+        }
+        let existed = match op {
+            BatchOp::Put { key, value, ttl_seconds } => {
+                let existed = txn_mgr.read_untracked(transaction_id, &key)?.is_some();
+                txn_mgr.execute_write_operation_with_ttl(transaction_id, key, value, ttl_seconds.map(Duration::from_secs))?;
+                existed
+            }
+            BatchOp::Delete { key } => txn_mgr.delete(transaction_id, key)?,
+        };
+        results.push(BatchOpResult { key, existed });
+    }
+    Ok(results)
+}
 
 pub struct ApiMgr {
-    // ApiMgr 现在拥有一个 TxnMgr 实例
-    // 为了简单起见，我们使其可变，以便调用 TxnMgr 的可变方法
-    // 在更复杂的设计中，这可能通过 Arc<Mutex<TxnMgr>> 或类似方式处理并发
+    // ApiMgr 拥有一个 TxnMgr 实例。TxnMgr 内部自己加锁，所有方法都只需要 &self，
+    // 因此 ApiMgr 可以直接放在 Arc 中被多个请求同时使用
     txn_mgr: TxnMgr,
 }
 
+impl Default for ApiMgr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ApiMgr {
     // 构造函数，用于创建 ApiMgr 实例
     pub fn new() -> Self {
@@ -19,6 +85,14 @@ impl ApiMgr {
         }
     }
 
+    // 打开持久化数据库：从 data_dir 加载已有数据并通过预写日志恢复，之后的提交都记录在日志中
+    pub fn open<P: AsRef<Path>>(data_dir: P) -> Result<Self, Error> {
+        println!("ApiMgr initialized, opening data directory '{}'.", data_dir.as_ref().display());
+        Ok(ApiMgr {
+            txn_mgr: TxnMgr::open(data_dir)?,
+        })
+    }
+
     // connect 和 disconnect 方法可以保持不变，或者根据需要调整
     // 在当前任务中，它们不是主要焦点
     pub fn connect() {
@@ -35,131 +109,331 @@ impl ApiMgr {
     }
 
     // put 方法现在将使用 TxnMgr
-    // 注意：为了让 put 能够修改 txn_mgr，它需要接收 &mut self
-    // 这意味着 main.rs 中调用 put 的方式也需要改变 (需要一个 ApiMgr 实例)
-    pub fn put(&mut self, transaction_id: u64, key: String, value: String) {
+    pub fn put(&self, transaction_id: u64, key: String, value: String) -> Result<(), Error> {
         println!("ApiMgr: Received put request for key: '{}', value: '{}' within transaction {}", key, value, transaction_id);
 
         // 不再在 put 内部自动开始和提交事务
         // 调用者负责事务的生命周期管理
 
         // 执行数据写入操作 (通过 TxnMgr)
-        self.txn_mgr.execute_write_operation(transaction_id, key.clone(), value.clone());
+        self.txn_mgr.execute_write_operation(transaction_id, key.clone(), value.clone())?;
         println!("ApiMgr: Write operation for key '{}' sent to TxnMgr for transaction {}.", key, transaction_id);
+        Ok(())
     }
 
-    // 其他方法暂时保持不变，但如果它们需要与事务交互，也需要 &mut self
-    // 并且需要通过 self.txn_mgr 调用相应的方法
+    // 写入一个在 ttl 之后过期的值；过期后对所有读取都不可见，并由后台任务清除
+    pub fn put_with_ttl(&self, transaction_id: u64, key: String, value: String, ttl: Option<Duration>) -> Result<(), Error> {
+        println!("ApiMgr: Received put request for key: '{}' with TTL {:?} within transaction {}", key, ttl, transaction_id);
+        self.txn_mgr.execute_write_operation_with_ttl(transaction_id, key, value, ttl)
+    }
 
-    pub fn begin_transaction(&mut self) -> u64 { // 改为实例方法并返回事务ID
+    // 清除已过期的键 (服务器中由后台任务定期调用)，返回清除的键数量
+    pub fn evict_expired(&self) -> Result<usize, Error> {
+        self.txn_mgr.evict_expired()
+    }
+
+    pub fn begin_transaction(&self) -> Result<u64, Error> { // 改为实例方法并返回事务ID
         println!("ApiMgr: Explicitly beginning a new transaction...");
         self.txn_mgr.begin_transaction()
     }
 
-    pub fn commit_transaction(&mut self, transaction_id: u64) { // 改为实例方法
+    // 开始一个事务并覆盖默认超时时间 (None 表示不超时)
+    pub fn begin_transaction_with_timeout(&self, timeout: Option<Duration>) -> Result<u64, Error> {
+        println!("ApiMgr: Explicitly beginning a new transaction with timeout {:?}...", timeout);
+        self.txn_mgr.begin_transaction_with_timeout(timeout)
+    }
+
+    // 按选项开始一个事务 (模式、隔离级别和超时时间)
+    pub fn begin_transaction_with_options(&self, options: BeginOptions) -> Result<u64, Error> {
+        println!("ApiMgr: Explicitly beginning a new transaction with {:?}...", options);
+        self.txn_mgr.begin_transaction_with_options(options)
+    }
+
+    pub fn default_begin_options(&self) -> BeginOptions {
+        self.txn_mgr.default_begin_options()
+    }
+
+    pub fn set_default_transaction_timeout(&self, timeout: Option<Duration>) {
+        self.txn_mgr.set_default_timeout(timeout);
+    }
+
+    pub fn default_transaction_timeout(&self) -> Option<Duration> {
+        self.txn_mgr.default_timeout()
+    }
+
+    // 回滚所有超时的事务 (服务器中由后台任务定期调用)
+    pub fn reap_expired_transactions(&self) -> Vec<u64> {
+        self.txn_mgr.reap_expired_transactions()
+    }
+
+    // 保存点：在事务中部分回滚
+    pub fn create_savepoint(&self, transaction_id: u64, name: String) -> Result<(), Error> {
+        println!("ApiMgr: Creating savepoint '{}' in transaction {}", name, transaction_id);
+        self.txn_mgr.create_savepoint(transaction_id, name)
+    }
+
+    pub fn rollback_to_savepoint(&self, transaction_id: u64, name: String) -> Result<(), Error> {
+        println!("ApiMgr: Rolling back transaction {} to savepoint '{}'", transaction_id, name);
+        self.txn_mgr.rollback_to_savepoint(transaction_id, &name)
+    }
+
+    pub fn release_savepoint(&self, transaction_id: u64, name: String) -> Result<(), Error> {
+        println!("ApiMgr: Releasing savepoint '{}' in transaction {}", name, transaction_id);
+        self.txn_mgr.release_savepoint(transaction_id, &name)
+    }
+
+    pub fn savepoints(&self, transaction_id: u64) -> Result<Vec<String>, Error> {
+        self.txn_mgr.savepoints(transaction_id)
+    }
+
+    pub fn commit_transaction(&self, transaction_id: u64) -> Result<(), Error> { // 改为实例方法
         println!("ApiMgr: Explicitly committing transaction {}...", transaction_id);
-        self.txn_mgr.commit_transaction(transaction_id);
+        self.txn_mgr.commit_transaction(transaction_id)
     }
 
-    pub fn rollback_transaction(&mut self, transaction_id: u64) { // 改为实例方法
+    pub fn rollback_transaction(&self, transaction_id: u64) -> Result<(), Error> { // 改为实例方法
         println!("ApiMgr: Explicitly rolling back transaction {}...", transaction_id);
-        self.txn_mgr.rollback_transaction(transaction_id);
+        self.txn_mgr.rollback_transaction(transaction_id)
     }
 
     // 实现 get 方法
+    // 不在事务中读取最新提交的数据。只读访问不需要开始事务
     pub fn get(&self, key: String) -> Option<String> {
-        println!("ApiMgr: Getting value for key: '{}'", key);
-        // 对于读取操作，也应该在事务上下文中执行
-        // 简单起见，我们在这里创建一个隐式事务
-        // 注意：TxnMgr::begin_transaction 需要 &mut self，但 get 是 &self
-        // 这意味着 TxnMgr 的读取操作也应该是 &self，或者我们需要调整 ApiMgr 的可变性
-        // 假设 TxnMgr::read_operation 是 &self (已在 TxnMgr 中调整)
-        // 并且读取操作不需要显式启动和提交事务（或者由 TxnMgr 内部处理）
-        // 为了简化，我们假设 TxnMgr 的 read_operation 可以直接调用
-        // 并且它会处理任何必要的事务隔离（即使当前实现很简单）
-
-        // 在更严格的事务模型中，get 可能需要 &mut self 来启动事务，
-        // 或者 TxnMgr 需要一种方式来处理只读事务。
-        // 当前 TxnMgr::read_operation 接受 transaction_id，所以我们需要一个。
-        // 让我们模拟一个只读事务的上下文。
-        // 由于 begin_transaction 是 &mut self，我们不能直接在 &self get 中调用它。
-        // 这是一个设计上的冲突点。
-        // 解决方案1: 使 get 变为 &mut self。
-        // 解决方案2: TxnMgr 提供一个不需要 &mut self 的方式来获取只读事务ID或执行只读操作。
-        // 解决方案3: ApiMgr 内部持有一个可变的 TxnMgr 引用 (例如 Arc<Mutex<TxnMgr>>), 但这会增加复杂性。
-
-        // 暂时采用一个简化的方法：假设 TxnMgr 的 read_operation 可以处理这种情况。
-        // 我们将传递一个临时的或默认的事务ID（例如 0）或者调整 TxnMgr::read_operation
-        // 使其在没有活动事务时也能读取（例如，读取已提交的数据）。
-        // 根据 TxnMgr::read_operation 的当前实现，它需要一个 transaction_id。
-        // 我们不能在这里调用 self.txn_mgr.begin_transaction() 因为 get 是 &self。
-
-        // 修正：让 get 方法也获取一个事务ID，或者让它内部管理一个短生命周期的事务。
-        // 为了与 put 的模式保持一致，并且考虑到 TxnMgr 的设计，
-        // get 方法也应该在一个事务的上下文中操作。
-        // 最简单的做法是让调用者负责事务管理，或者 ApiMgr 内部为 get 创建一个事务。
-        // 如果 ApiMgr 为 get 创建事务，那么 get 需要是 &mut self。
-
-        // 按照指示，此方法应通过 TxnMgr 调用其 read_operation。
-        // TxnMgr::read_operation(transaction_id: u64, key: &String)
-        // 我们需要一个 transaction_id。
-        // 让我们假设对于简单的 get，我们可以使用一个临时的事务。
-        // 这仍然需要 &mut self.txn_mgr.begin_transaction()。
-        // 因此，get 必须是 &mut self。
-
-        // **** 更正：根据任务描述，ApiMgr::get 应该通过 TxnMgr 调用其 read_operation。
-        // TxnMgr::read_operation(&self, transaction_id: u64, key: &String)
-        // 这意味着调用 ApiMgr::get 的代码需要先启动一个事务。
-        // 或者，ApiMgr::get 内部启动和提交一个事务。
-        // 为了简单和与 put 一致，让 ApiMgr::get 内部处理事务。
-        // 这就需要 ApiMgr::get 是 &mut self。
-
-        // 再次思考：如果 get 只是读取，它理论上不应该改变 ApiMgr 的状态，所以 &self 更合适。
-        // 这意味着 TxnMgr::read_operation 应该能够在没有显式可变引用的情况下被调用，
-        // 并且能够处理事务上下文（例如，读取最新提交的数据或在特定快照上读取）。
-        // 当前 TxnMgr::read_operation 是 &self，但它需要一个 transaction_id。
-        // 这个 transaction_id 从哪里来？
-        // 选项 A: get 也接收 transaction_id: pub fn get(&self, transaction_id: u64, key: String) -> Option<String>
-        // 选项 B: get 内部创建一个只读事务。这通常需要 TxnMgr 支持。
-        // 选项 C: 简化，假设一个默认的事务ID或上下文。
-
-        // 鉴于 TxnMgr::read_operation(&self, transaction_id: u64, key: &String)
-        // 并且我们希望 ApiMgr::get(&self, key: String)
-        // 最直接的方式是 ApiMgr::get 内部不处理事务的开始和结束，
-        // 而是依赖于一个已经存在的事务上下文，或者 TxnMgr::read_operation 能够处理 "无事务" 或 "默认事务" 的情况。
-        // 我们的 TxnMgr::read_operation 当前忽略 transaction_id 进行实际读取，但打印它。
-        // 我们可以传递一个虚拟的 transaction_id。
-
-        // 让我们遵循最初的意图，即 ApiMgr::get 通过 TxnMgr 调用 read_operation。
-        // 为了简单起见，我们假设 get 操作是在一个 "默认" 或 "即时" 的事务上下文中。
-        // 我们将传递一个固定的事务ID（例如 0）给 TxnMgr::read_operation，
-        // 或者修改 TxnMgr::read_operation 以便在特定情况下不需要事务ID。
-        // 当前 TxnMgr::read_operation 接受 transaction_id，所以我们必须提供一个。
-        // 传递 0 作为 "当前" 或 "最新提交" 数据的读取请求。
-        let pseudo_transaction_id_for_read = 0; // 代表读取最新提交数据
-        self.txn_mgr.read_operation(pseudo_transaction_id_for_read, &key)
-    }
-
-    pub fn delete(&mut self, key: String) { // &mut self
-        println!("ApiMgr: Deleting key: {} (requires transaction handling)...", key);
-        let transaction_id = self.txn_mgr.begin_transaction();
-        self.txn_mgr.delete(transaction_id, key);
-        self.txn_mgr.commit_transaction(transaction_id);
-    }
-
-    // 快照和查询方法也需要根据事务管理进行调整
-    pub fn create_snapshot(&mut self, snapshot_id: String) {
-        println!("ApiMgr: Creating snapshot with ID: {} (requires transaction/storage interaction)...", snapshot_id);
-        // TODO: 可能需要调用 TxnMgr 或 StorageEngine 的相关方法
-    }
-
-    pub fn restore_snapshot(&mut self, snapshot_id: String) {
-        println!("ApiMgr: Restoring snapshot with ID: {} (requires transaction/storage interaction)...", snapshot_id);
-        // TODO: 可能需要调用 TxnMgr 或 StorageEngine 的相关方法
+        self.txn_mgr.read_committed(&key)
+    }
+
+    // 在指定事务的快照中读取 (能看到该事务自己尚未提交的写入)
+    pub fn get_in_transaction(&self, transaction_id: u64, key: String) -> Result<Option<String>, Error> {
+        self.txn_mgr.read_operation(transaction_id, &key)
+    }
+
+    // 按键的顺序扫描；提供 transaction_id 时在该事务的快照中扫描 (包括它自己的写入)，否则扫描最新提交的数据
+    pub fn scan(&self, transaction_id: Option<u64>, range: &KeyRange, limit: usize) -> Result<Vec<(String, String)>, Error> {
+        match transaction_id {
+            Some(transaction_id) => self.txn_mgr.scan(transaction_id, range, limit),
+            None => Ok(self.txn_mgr.scan_committed(range, limit)),
+        }
+    }
+
+    // 订阅范围内的已提交变更。提供 since 时先返回提交时间戳大于 since 的历史变更，
+    // 之后的变更从返回的 Receiver 中获得 (需要调用者自己按范围过滤)；两者之间不会遗漏或重复
+    pub fn watch(
+        &self,
+        range: &KeyRange,
+        since: Option<u64>,
+    ) -> Result<(Vec<ChangeEvent>, broadcast::Receiver<ChangeEvent>), Error> {
+        println!("ApiMgr: Watching {:?} since {:?}", range, since);
+        self.txn_mgr.subscribe_since(range, since)
+    }
+
+    // 时间点读取：返回提交时间戳 ts 时的值
+    pub fn get_as_of(&self, key: String, ts: u64) -> Result<Option<String>, Error> {
+        self.txn_mgr.read_as_of(&key, ts)
+    }
+
+    // 时间点读取：返回事务 transaction_id 提交之后 (在下一次提交之前) 的值
+    pub fn get_as_of_transaction(&self, key: String, transaction_id: u64) -> Result<Option<String>, Error> {
+        let commit_ts = self.txn_mgr.transaction_commit_ts(transaction_id)?;
+        println!("ApiMgr: Transaction {} committed at ts {}", transaction_id, commit_ts);
+        self.get_as_of(key, commit_ts)
+    }
+
+    // 键的全部版本 (包括删除标记)，从旧到新
+    pub fn history(&self, key: String) -> Vec<Version> {
+        println!("ApiMgr: Getting history for key: '{}'", key);
+        self.txn_mgr.history(&key)
+    }
+
+    pub fn set_retention_policy(&self, policy: RetentionPolicy) {
+        self.txn_mgr.set_retention_policy(policy);
+    }
+
+    // 按保留策略回收历史版本 (服务器中由后台任务定期调用)
+    pub fn collect_garbage(&self) -> GcStats {
+        println!("ApiMgr: Running version garbage collection...");
+        self.txn_mgr.collect_garbage()
+    }
+
+    pub fn gc_metrics(&self) -> GcMetrics {
+        self.txn_mgr.gc_metrics()
+    }
+
+    // 在调用者提供的事务中执行 op；没有提供事务时在一个新事务中执行并自动提交，失败时回滚
+    fn in_transaction<T>(
+        &self,
+        transaction_id: Option<u64>,
+        op: impl FnOnce(&TxnMgr, u64) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if let Some(transaction_id) = transaction_id {
+            return op(&self.txn_mgr, transaction_id);
+        }
+        let transaction_id = self.txn_mgr.begin_transaction()?;
+        match op(&self.txn_mgr, transaction_id) {
+            Ok(result) => self.txn_mgr.commit_transaction(transaction_id).map(|_| result),
+            Err(e) => {
+                let _ = self.txn_mgr.rollback_transaction(transaction_id);
+                Err(e)
+            }
+        }
+    }
+
+    // 删除键：提供 transaction_id 时在调用者的事务中删除 (提交前对其他事务不可见，回滚后键仍然存在)，
+    // 否则在一个自动提交的事务中删除。返回删除前该键在事务中是否可见
+    pub fn delete(&self, transaction_id: Option<u64>, key: String) -> Result<bool, Error> {
+        println!("ApiMgr: Deleting key: {} in transaction {:?}", key, transaction_id);
+        // 不存在的键不需要删除，也就不必为它开始一个事务
+        if transaction_id.is_none() && self.txn_mgr.read_committed(&key).is_none() {
+            return Ok(false);
+        }
+        self.in_transaction(transaction_id, |txn_mgr, transaction_id| txn_mgr.delete(transaction_id, key))
+    }
+
+    // 原子地应用一组写入和删除：提供 transaction_id 时在该事务中执行 (由调用者提交)，
+    // 否则在一个新事务中执行并提交；任一操作失败时整批都不会生效，超过 MAX_BATCH_OPERATIONS 时整批被拒绝
+    pub fn put_many(&self, transaction_id: Option<u64>, ops: Vec<BatchOp>) -> Result<Vec<BatchOpResult>, Error> {
+        println!("ApiMgr: Applying batch of {} operation(s) in transaction {:?}", ops.len(), transaction_id);
+        if ops.len() > MAX_BATCH_OPERATIONS {
+            return Err(Error::BatchTooLarge { operations: ops.len(), max: MAX_BATCH_OPERATIONS });
+        }
+        let Some(transaction_id) = transaction_id else {
+            return self.in_transaction(None, |txn_mgr, transaction_id| apply_batch(txn_mgr, transaction_id, ops));
+        };
+        // 调用者的事务在失败后仍然可以继续使用，因此用保存点撤销本批次中已经执行的操作
+        self.txn_mgr.create_savepoint(transaction_id, BATCH_SAVEPOINT.to_string())?;
+        match apply_batch(&self.txn_mgr, transaction_id, ops) {
+            Ok(results) => {
+                self.txn_mgr.release_savepoint(transaction_id, BATCH_SAVEPOINT)?;
+                Ok(results)
+            }
+            Err(e) => {
+                let _ = self.txn_mgr.rollback_to_savepoint(transaction_id, BATCH_SAVEPOINT);
+                let _ = self.txn_mgr.release_savepoint(transaction_id, BATCH_SAVEPOINT);
+                Err(e)
+            }
+        }
+    }
+
+    // 条件写入：只有键的当前值等于 expected (None 表示键不存在) 时才写入 new_value (None 表示删除)，
+    // 否则返回 PreconditionFailed
+    pub fn compare_and_swap(
+        &self,
+        transaction_id: Option<u64>,
+        key: String,
+        expected: Option<String>,
+        new_value: Option<String>,
+    ) -> Result<(), Error> {
+        println!("ApiMgr: Compare-and-swap on key: '{}' (expected {:?}) in transaction {:?}", key, expected, transaction_id);
+        self.in_transaction(transaction_id, |txn_mgr, transaction_id| {
+            txn_mgr.compare_and_swap(transaction_id, key, expected.as_deref(), new_value)
+        })
+    }
+
+    pub fn put_if_absent(&self, transaction_id: Option<u64>, key: String, value: String) -> Result<(), Error> {
+        self.compare_and_swap(transaction_id, key, None, Some(value))
+    }
+
+    pub fn delete_if_equals(&self, transaction_id: Option<u64>, key: String, expected: String) -> Result<(), Error> {
+        self.compare_and_swap(transaction_id, key, Some(expected), None)
+    }
+
+    // 原子地把整数值加上 delta (键不存在时视为 0)，返回新值
+    pub fn increment(&self, transaction_id: Option<u64>, key: String, delta: i64) -> Result<i64, Error> {
+        println!("ApiMgr: Incrementing key: '{}' by {} in transaction {:?}", key, delta, transaction_id);
+        self.in_transaction(transaction_id, |txn_mgr, transaction_id| txn_mgr.increment(transaction_id, key, delta))
+    }
+
+    // 命名快照：记录当前最后一次提交的时间戳，数据本身通过版本历史保留
+    pub fn create_snapshot(&self, snapshot_id: String) -> Result<SnapshotInfo, Error> {
+        println!("ApiMgr: Creating snapshot with ID: {}", snapshot_id);
+        self.txn_mgr.create_snapshot(snapshot_id)
+    }
+
+    pub fn list_snapshots(&self) -> Vec<SnapshotInfo> {
+        self.txn_mgr.list_snapshots()
+    }
+
+    pub fn restore_snapshot(&self, snapshot_id: String) -> Result<(), Error> {
+        println!("ApiMgr: Restoring snapshot with ID: {}", snapshot_id);
+        self.txn_mgr.restore_snapshot(&snapshot_id)
+    }
+
+    pub fn delete_snapshot(&self, snapshot_id: String) -> Result<(), Error> {
+        println!("ApiMgr: Deleting snapshot with ID: {}", snapshot_id);
+        self.txn_mgr.delete_snapshot(&snapshot_id)
     }
 
     pub fn execute_query(&self, query_string: String) {
         println!("ApiMgr: Executing query: {} (requires query processor and transaction handling)...", query_string);
         // TODO: 实现查询逻辑
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put(key: &str, value: &str) -> BatchOp {
+        BatchOp::Put { key: key.to_string(), value: value.to_string(), ttl_seconds: None }
+    }
+
+    fn delete(key: &str) -> BatchOp {
+        BatchOp::Delete { key: key.to_string() }
+    }
+
+    #[test]
+    fn test_put_many_reports_whether_keys_existed() {
+        let api_mgr = ApiMgr::new();
+        api_mgr.put_many(None, vec![put("a", "1")]).unwrap();
+
+        let results = api_mgr.put_many(None, vec![put("a", "2"), put("b", "1"), delete("b"), delete("c")]).unwrap();
+        let existed: Vec<(&str, bool)> = results.iter().map(|result| (result.key.as_str(), result.existed)).collect();
+        assert_eq!(existed, vec![("a", true), ("b", false), ("b", true), ("c", false)]);
+        assert_eq!(api_mgr.get("a".to_string()), Some("2".to_string()));
+        assert_eq!(api_mgr.get("b".to_string()), None);
+    }
+
+    #[test]
+    fn test_failed_batch_applies_nothing() {
+        let api_mgr = ApiMgr::new();
+        api_mgr.put_many(None, vec![put("a", "1")]).unwrap();
+
+        let result = api_mgr.put_many(None, vec![put("a", "2"), delete("a"), put("b", "1"), put("", "x")]);
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+        assert_eq!(api_mgr.get("a".to_string()), Some("1".to_string()));
+        assert_eq!(api_mgr.get("b".to_string()), None);
+    }
+
+    #[test]
+    fn test_failed_batch_in_caller_transaction_keeps_only_earlier_writes() {
+        let api_mgr = ApiMgr::new();
+        let transaction_id = api_mgr.begin_transaction().unwrap();
+        api_mgr.put(transaction_id, "a".to_string(), "before".to_string()).unwrap();
+        api_mgr.create_savepoint(transaction_id, "sp".to_string()).unwrap();
+
+        let result = api_mgr.put_many(Some(transaction_id), vec![put("a", "batch"), put("b", "batch"), put("", "x")]);
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+        // 事务仍然可用，批次之前的写入和保存点都保留
+        assert_eq!(api_mgr.get_in_transaction(transaction_id, "a".to_string()), Ok(Some("before".to_string())));
+        assert_eq!(api_mgr.get_in_transaction(transaction_id, "b".to_string()), Ok(None));
+        assert_eq!(api_mgr.savepoints(transaction_id), Ok(vec!["sp".to_string()]));
+
+        api_mgr.put_many(Some(transaction_id), vec![put("b", "retried")]).unwrap();
+        assert_eq!(api_mgr.savepoints(transaction_id), Ok(vec!["sp".to_string()]));
+        api_mgr.commit_transaction(transaction_id).unwrap();
+        assert_eq!(api_mgr.get("a".to_string()), Some("before".to_string()));
+        assert_eq!(api_mgr.get("b".to_string()), Some("retried".to_string()));
+    }
+
+    #[test]
+    fn test_put_many_rejects_batches_over_the_limit() {
+        let api_mgr = ApiMgr::new();
+        let ops: Vec<BatchOp> = (0..=MAX_BATCH_OPERATIONS).map(|i| put(&format!("key{}", i), "v")).collect();
+        assert_eq!(
+            api_mgr.put_many(None, ops),
+            Err(Error::BatchTooLarge { operations: MAX_BATCH_OPERATIONS + 1, max: MAX_BATCH_OPERATIONS })
+        );
+        assert_eq!(api_mgr.get("key0".to_string()), None);
+
+        let ops: Vec<BatchOp> = (0..MAX_BATCH_OPERATIONS).map(|i| put(&format!("key{}", i), "v")).collect();
+        assert_eq!(api_mgr.put_many(None, ops).unwrap().len(), MAX_BATCH_OPERATIONS);
+    }
+}
@@ -1,7 +1,8 @@
 use std::fs;
 use std::collections::HashMap;
 use clap::Parser;
-use skdb::{parse_dsl_input, execute_query, execute_update, execute_add, DslStatement, DslRoot, Table, Row, HeaderField, Value, TableData};
+use skdb::parser::string_literal;
+use skdb::{parse_dsl_input_with_options, ParseOptions, execute_query, execute_update, execute_add, DslStatement, DslRoot, Row, HeaderField, Value, TableData};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -10,6 +11,10 @@ struct Args {
     #[clap(short, long, value_parser)]
     file: String,
 
+    /// Check every table against its header (types, duplicate columns and keys, short rows)
+    #[clap(long)]
+    strict: bool,
+
     /// DSL statements to execute (queries or updates)
     #[clap(value_parser, required = true, num_args = 1..)]
     statements: Vec<String>,
@@ -26,9 +31,10 @@ fn run() -> Result<(), String> {
     };
 
     let mut data_root: DslRoot = HashMap::new();
+    let options = ParseOptions { strict: args.strict };
     
     println!("--- Parsing input file: {} ---", args.file);
-    match parse_dsl_input(&file_content, None) { 
+    match parse_dsl_input_with_options(&file_content, options) { 
         Ok(parsed_file_statements) => {
             let mut definitions = Vec::new();
             let mut copy_ops = Vec::new();
@@ -50,7 +56,7 @@ fn run() -> Result<(), String> {
                     println!("CLI: Processing #复制结构: source_table='{}', source_path='{}', target_table='{}'", source_table_name, source_path, target_table_name);
                      match fs::read_to_string(&source_path) {
                         Ok(src_file_content) => {
-                            match parse_dsl_input(&src_file_content, None) {
+                            match parse_dsl_input_with_options(&src_file_content, options) {
                                 Ok(source_statements) => {
                                     let mut found = false;
                                     for s_stmt in source_statements {
@@ -71,7 +77,7 @@ fn run() -> Result<(), String> {
                                     }
                                     if !found { eprintln!("CLI Error: Source table '{}' not found in '{}' for #复制结构.", source_table_name, source_path); }
                                 }
-                                Err(e) => {eprintln!("CLI Error: Parsing source file '{}' for #复制结构: {}", source_path, e);}
+                                Err(e) => {eprintln!("CLI Error: Parsing source file '{}' for #复制结构:\n\n{}", source_path, e.with_file(&source_path).render());}
                             }
                         }
                         Err(e) => {eprintln!("CLI Error: Reading source file '{}' for #复制结构: {}", source_path, e);}
@@ -84,7 +90,7 @@ fn run() -> Result<(), String> {
                     println!("CLI: Processing #引用: source_table='{}', source_path='{}', target_table='{}'", source_table_name, source_path, target_table_name);
                     match fs::read_to_string(&source_path) {
                         Ok(src_file_content) => {
-                            match parse_dsl_input(&src_file_content, None) {
+                            match parse_dsl_input_with_options(&src_file_content, options) {
                                 Ok(source_statements) => {
                                     let mut found = false;
                                     for s_stmt in source_statements {
@@ -101,7 +107,7 @@ fn run() -> Result<(), String> {
                                     }
                                      if !found { eprintln!("CLI Error: Source table '{}' not found in '{}' for #引用.", source_table_name, source_path); }
                                 }
-                                Err(e) => {eprintln!("CLI Error: Parsing source file '{}' for #引用: {}", source_path, e);}
+                                Err(e) => {eprintln!("CLI Error: Parsing source file '{}' for #引用:\n\n{}", source_path, e.with_file(&source_path).render());}
                             }
                         }
                         Err(e) => {eprintln!("CLI Error: Reading source file '{}' for #引用: {}", source_path, e);}
@@ -121,7 +127,7 @@ fn run() -> Result<(), String> {
                                     let mut parsed_rows: Vec<skdb::Row> = Vec::new();
                                      for line_str in raw_lines {
                                         if line_str.trim().is_empty() || line_str.trim().starts_with('#') { continue; }
-                                        match skdb::parser::parse_data_line(&line_str, &existing_table.headers, &existing_table.header_map) {
+                                        match skdb::parser::parse_data_line(line_str, &existing_table.headers, &existing_table.header_map) {
                                             Ok(row) => parsed_rows.push(row),
                                             Err(e) => eprintln!("CLI Error: Parsing data line '{}' for '{}': {}", line_str, name, e),
                                         }
@@ -180,7 +186,7 @@ fn run() -> Result<(), String> {
             }
         }
         Err(e) => {
-            return Err(format!("Error parsing input file '{}': {}", args.file, e));
+            return Err(format!("Error parsing input file '{}':\n\n{}", args.file, e.with_file(&args.file).render()));
         }
     }
 
@@ -225,18 +231,14 @@ fn run() -> Result<(), String> {
 
     fn format_value(value: &Value) -> String {
         match value {
-            Value::String(s) => {
-                // Consistent with how parser.rs's parse_value_str expects quoted strings
-                // if they contain special characters or are ambiguous.
-                if s.contains(',') || s.contains('(') || s.contains(')') || s.contains('"') || s.starts_with(' ') || s.ends_with(' ') || s.is_empty() {
-                    format!("\"{}\"", s.replace("\"", "\\\"")) // Escape inner quotes
-                } else {
-                    s.clone()
-                }
-            }
+            Value::String(s) => string_literal(s), // Quoted and escaped when needed to read back unchanged
             Value::Integer(i) => i.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Float(f) => format!("{:?}", f),
+            Value::Date(date) => date.to_string(),
+            Value::DateTime(datetime) => datetime.to_string(),
             Value::Tuple(values) => {
-                let inner: Vec<String> = values.iter().map(|v| format_value(v)).collect(); // Recursive call
+                let inner: Vec<String> = values.iter().map(format_value).collect(); // Recursive call
                 format!("({})", inner.join(","))
             }
             Value::Reference { type_name, key } => {
@@ -317,7 +319,7 @@ fn run() -> Result<(), String> {
         if !temp_content.ends_with('\n') { 
             temp_content.push('\n');
         }
-        let mut trimmed_content = temp_content.trim_end_matches(|c: char| c == '\n' || c == '\r').to_string();
+        let mut trimmed_content = temp_content.trim_end_matches(['\n', '\r']).to_string();
         trimmed_content.push('\n');
         trimmed_content
     };
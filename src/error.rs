@@ -0,0 +1,111 @@
+// src/error.rs
+
+// skdb 的统一错误类型：存储引擎、预写日志、事务管理器和 ApiMgr 都返回 Error，
+// web_server 再把它映射为 HTTP 状态码和 ApiResponse 中的 error 字段
+use std::fmt;
+use std::io;
+
+// 找不到或已经存在的对象
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resource {
+    Transaction(u64),
+    Snapshot(String),
+    Savepoint { transaction_id: u64, name: String },
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resource::Transaction(id) => write!(f, "Transaction ID {}", id),
+            Resource::Snapshot(id) => write!(f, "Snapshot '{}'", id),
+            Resource::Savepoint { transaction_id, name } => {
+                write!(f, "Savepoint '{}' in transaction ID {}", name, transaction_id)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    NotFound(Resource),      // 从未开始过的事务、不存在的快照或保存点
+    AlreadyExists(Resource), // 快照重名
+    TxnNotActive(u64),       // 已提交或已回滚的事务 ID
+    TxnTimedOut(u64),        // 事务超过截止时间，已被自动回滚
+    TxnReadOnly(u64),        // 在只读事务中写入
+    TxnNotCommitted(u64),    // 还没有提交的事务没有提交时间戳
+    Conflict { transaction_id: u64, key: String }, // 写写冲突：另一个事务在本事务开始后先提交了对同一个键的修改
+    SerializationFailure { transaction_id: u64, key: String }, // 可串行化事务读过的键或范围在它开始后被其他事务修改
+    PreconditionFailed { key: String, actual: Option<String> }, // 条件写入时键的当前值与预期不符
+    TypeMismatch { key: String, reason: String }, // 键的值不适用于该操作 (例如对非整数值做自增)
+    FutureTimestamp(u64), // 时间点读取的时间戳晚于最后一次提交
+    HistoryPruned(u64),   // 该时间戳的版本已被垃圾回收
+    Parse(String),        // 请求中格式错误的参数
+    InvalidArgument(String), // 格式正确但不被接受的参数组合
+    BatchTooLarge { operations: usize, max: usize },
+    Io { kind: io::ErrorKind, message: String }, // 读写数据文件或预写日志失败
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(resource) => write!(f, "{} not found", resource),
+            Error::AlreadyExists(resource) => write!(f, "{} already exists", resource),
+            Error::TxnNotActive(id) => write!(f, "Transaction ID {} is no longer active", id),
+            Error::TxnTimedOut(id) => write!(f, "Transaction ID {} exceeded its deadline and was rolled back", id),
+            Error::TxnReadOnly(id) => write!(f, "Transaction ID {} is read-only", id),
+            Error::TxnNotCommitted(id) => write!(f, "Transaction {} has not committed", id),
+            Error::Conflict { transaction_id, key } => write!(
+                f,
+                "Transaction ID {} aborted: key '{}' was modified by a transaction that committed first",
+                transaction_id, key
+            ),
+            Error::SerializationFailure { transaction_id, key } => write!(
+                f,
+                "Transaction ID {} aborted: key '{}' it read was modified by a concurrent transaction, retry it",
+                transaction_id, key
+            ),
+            Error::PreconditionFailed { key, actual: Some(actual) } => {
+                write!(f, "Precondition failed for key '{}': current value is '{}'", key, actual)
+            }
+            Error::PreconditionFailed { key, actual: None } => {
+                write!(f, "Precondition failed for key '{}': key does not exist", key)
+            }
+            Error::TypeMismatch { key, reason } => write!(f, "Invalid value for key '{}': {}", key, reason),
+            Error::FutureTimestamp(ts) => write!(f, "Timestamp {} is later than the last commit", ts),
+            Error::HistoryPruned(ts) => write!(f, "Versions as of timestamp {} have been garbage collected", ts),
+            Error::Parse(msg) => write!(f, "Parse error: {}", msg),
+            Error::InvalidArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            Error::BatchTooLarge { operations, max } => {
+                write!(f, "A batch may contain at most {} operations (got {})", max, operations)
+            }
+            Error::Io { message, .. } => write!(f, "I/O error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io { kind: err.kind(), message: err.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors_keep_their_kind() {
+        let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        assert_eq!(err, Error::Io { kind: io::ErrorKind::NotFound, message: "no such file".to_string() });
+        assert_eq!(err.to_string(), "I/O error: no such file");
+    }
+
+    #[test]
+    fn test_display_names_the_missing_resource() {
+        let err = Error::NotFound(Resource::Savepoint { transaction_id: 7, name: "sp".to_string() });
+        assert_eq!(err.to_string(), "Savepoint 'sp' in transaction ID 7 not found");
+        assert_eq!(Error::AlreadyExists(Resource::Snapshot("s".to_string())).to_string(), "Snapshot 's' already exists");
+    }
+}
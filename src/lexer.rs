@@ -0,0 +1,277 @@
+use crate::parse_error::ParseError;
+
+// --- Tokenizer for the .hs format ---
+//
+// The grammar is documented in docs/hs-grammar.md. Whitespace only separates tokens, and
+// comments (`#` or `//` outside a string, up to the end of the line) are dropped, except that
+// a `#` directly followed by `.` starts an update statement (`#.path = value`). Every line
+// end still produces a Newline token, because the format is line-oriented: the parser uses
+// them to tell statements, header lines and rows apart.
+//
+// Strings are quoted with '"' or '\'' and may span lines. Both kinds understand the same
+// escapes: \\ \" \' \n \r \t \0 and \u{1F600} (one to six hex digits).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,        // Any run of characters that are not whitespace or one of the symbols below
+    Str,         // "..." or '...', quotes included and escapes unresolved
+    Colon,       // :
+    DoubleColon, // ::
+    Slash,       // /
+    Comma,       // ,
+    LParen,      // (
+    RParen,      // )
+    Tilde,       // ~
+    Hash,        // # directly followed by '.'
+    Newline,
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub line: usize,   // 1-based; a string spanning lines belongs to the line it starts on
+    pub column: usize, // 1-based, counted in characters
+    pub start: usize,  // Byte range of `text` in the input
+    pub end: usize,
+}
+
+// Quotes only start a string at the beginning of a token, so "O'Brien" is a single word
+pub(crate) fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, ':' | '/' | ',' | '(' | ')' | '~' | '#')
+}
+
+// Reads the escape sequence after a backslash, returning the character and the number of
+// bytes of `rest` it used
+fn read_escape(rest: &str) -> Result<(char, usize), &'static str> {
+    let simple = match rest.chars().next() {
+        Some('\\') => '\\',
+        Some('"') => '"',
+        Some('\'') => '\'',
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('t') => '\t',
+        Some('0') => '\0',
+        Some('u') => {
+            let digits = rest.strip_prefix("u{").and_then(|rest| rest.split_once('}')).map(|(digits, _)| digits);
+            let Some(digits) = digits.filter(|digits| (1..=6).contains(&digits.len())) else {
+                return Err("Invalid unicode escape, expected \\u{XXXX} with 1 to 6 hex digits");
+            };
+            let c = u32::from_str_radix(digits, 16).ok().and_then(char::from_u32).ok_or("Invalid unicode code point")?;
+            return Ok((c, digits.len() + 3));
+        }
+        _ => return Err("Unknown escape sequence"),
+    };
+    Ok((simple, 1))
+}
+
+// The value of a string literal: `literal` without its quotes, with escapes resolved.
+// The lexer has already reported invalid escapes; they are kept as written.
+pub fn unescape(literal: &str) -> String {
+    let mut value = String::with_capacity(literal.len());
+    let mut rest = literal;
+    while let Some(backslash) = rest.find('\\') {
+        value.push_str(&rest[..backslash]);
+        rest = &rest[backslash + 1..];
+        match read_escape(rest) {
+            Ok((c, used)) => {
+                value.push(c);
+                rest = &rest[used..];
+            }
+            Err(_) => value.push('\\'),
+        }
+    }
+    value.push_str(rest);
+    value
+}
+
+// Writes `s` as a double-quoted string literal; unescape(quote(s)) gives back `s`
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl Token<'_> {
+    // The value of a Str token
+    pub fn string_value(&self) -> String {
+        unescape(&self.text[1..self.text.len() - 1])
+    }
+}
+
+// Splits the input into tokens, ending with Eof. Lexical errors (unterminated strings, bad
+// escapes) are collected and lexing goes on, so tokenizing never stops early.
+pub fn tokenize(input: &str) -> (Vec<Token<'_>>, Vec<ParseError>) {
+    let mut lexer = Lexer { input, pos: 0, line: 1, line_start: 0, tokens: Vec::new(), errors: Vec::new() };
+    lexer.run();
+    (lexer.tokens, lexer.errors)
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+    line_start: usize,
+    tokens: Vec<Token<'a>>,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    // The current line without its line ending
+    fn line_text(&self) -> &'a str {
+        let rest = &self.input[self.line_start..];
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        line.strip_suffix('\r').unwrap_or(line)
+    }
+
+    fn column(&self, offset: usize) -> usize {
+        self.input[self.line_start..offset].chars().count() + 1
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize) {
+        self.push_at(kind, start, self.line, self.column(start));
+    }
+
+    fn push_at(&mut self, kind: TokenKind, start: usize, line: usize, column: usize) {
+        self.tokens.push(Token { kind, text: &self.input[start..self.pos], line, column, start, end: self.pos });
+    }
+
+    fn error(&mut self, start: usize, end: usize, message: &str) {
+        let line = self.line_text();
+        let end = end.min(self.line_start + line.len()).max(start);
+        self.errors.push(ParseError::new(self.line, self.column(start), &self.input[start..end], line, message));
+    }
+
+    fn skip_to_line_end(&mut self) {
+        self.pos += self.input[self.pos..].find('\n').unwrap_or(self.input.len() - self.pos);
+    }
+
+    fn run(&mut self) {
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            self.pos += c.len_utf8();
+            match c {
+                '\n' => {
+                    self.push(TokenKind::Newline, start);
+                    self.line += 1;
+                    self.line_start = self.pos;
+                }
+                c if c.is_whitespace() => {}
+                '#' if self.peek() == Some('.') => self.push(TokenKind::Hash, start),
+                '#' => self.skip_to_line_end(),
+                '/' if self.peek() == Some('/') => self.skip_to_line_end(),
+                '/' => self.push(TokenKind::Slash, start),
+                ':' if self.peek() == Some(':') => {
+                    self.pos += 1;
+                    self.push(TokenKind::DoubleColon, start);
+                }
+                ':' => self.push(TokenKind::Colon, start),
+                ',' => self.push(TokenKind::Comma, start),
+                '(' => self.push(TokenKind::LParen, start),
+                ')' => self.push(TokenKind::RParen, start),
+                '~' => self.push(TokenKind::Tilde, start),
+                '"' | '\'' => self.string(start, c),
+                _ => {
+                    while self.peek().is_some_and(is_word_char) {
+                        self.pos += self.peek().map_or(0, char::len_utf8);
+                    }
+                    self.push(TokenKind::Word, start);
+                }
+            }
+        }
+        let end = self.pos;
+        self.push(TokenKind::Eof, end);
+    }
+
+    // `start` is the offset of the opening quote, which has been consumed
+    fn string(&mut self, start: usize, quote: char) {
+        let (line, line_start, column) = (self.line, self.line_start, self.column(start));
+        while let Some(c) = self.peek() {
+            let offset = self.pos;
+            self.pos += c.len_utf8();
+            match c {
+                c if c == quote => {
+                    self.push_at(TokenKind::Str, start, line, column);
+                    return;
+                }
+                '\\' => match read_escape(&self.input[self.pos..]) {
+                    Ok((_, used)) => self.pos += used,
+                    Err(message) => {
+                        let end = self.pos + self.peek().map_or(0, char::len_utf8);
+                        self.error(offset, end, message);
+                    }
+                },
+                '\n' => {
+                    self.line += 1;
+                    self.line_start = self.pos;
+                }
+                _ => {}
+            }
+        }
+        // Unterminated: report it on the line the string starts on and carry on after that line
+        (self.line, self.line_start, self.pos) = (line, line_start, start);
+        self.error(start, usize::MAX, "Unterminated string");
+        self.skip_to_line_end();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        tokenize(input).0.iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn test_comments_are_dropped_outside_strings() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("a:: b # c\n\"x # y // z\",(1) // w\n~"),
+            vec![Word, DoubleColon, Word, Newline, Str, Comma, LParen, Word, RParen, Newline, Tilde, Eof]
+        );
+        let (tokens, _) = tokenize("  名字,\"x # y\"");
+        assert_eq!((tokens[0].text, tokens[0].column), ("名字", 3));
+        assert_eq!((tokens[2].text, tokens[2].column, tokens[2].start), ("\"x # y\"", 6, 9));
+        assert_eq!(kinds("#.t[0].f = 1 # c\n# .x"), vec![Hash, Word, Word, Word, Newline, Eof]);
+    }
+
+    #[test]
+    fn test_quote_and_unescape_are_inverses() {
+        let s = "tab\there \"q\" 'a' \\ line\r\nbell\u{7} 数";
+        assert_eq!(quote(s), "\"tab\\there \\\"q\\\" 'a' \\\\ line\\r\\nbell\\u{7} 数\"");
+        assert_eq!(unescape(&quote(s)[1..quote(s).len() - 1]), s);
+        assert_eq!(unescape("\\u{1F600}\\0\\'"), "😀\0'");
+
+        let (tokens, errors) = tokenize("'a\nb\\'',x");
+        assert!(errors.is_empty());
+        assert_eq!((tokens[0].kind, tokens[0].string_value()), (TokenKind::Str, "a\nb'".to_string()));
+        assert_eq!((tokens[2].line, tokens[2].column), (2, 6));
+    }
+
+    #[test]
+    fn test_unterminated_string_is_reported_and_skipped() {
+        let (tokens, errors) = tokenize("a,\"bc\r\nd");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column, errors[0].text.as_str()), (1, 3, "\"bc"));
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+        assert_eq!(texts, vec!["a", ",", "\n", "d", ""]);
+    }
+}
@@ -4,16 +4,22 @@ pub mod transaction;
 pub mod storage;
 pub mod query_processor;
 pub mod log;
+pub mod error;
+
+pub use error::{Error, Resource};
 
 // DSL Parser Modules
 pub mod structs;
 pub mod parser;
+pub mod parse_error;
+pub mod lexer;
 pub mod query;
 
 // Public API for the DSL Parser
-pub use structs::{Value, DslRoot, Table, Row, HeaderField, TableData}; // Export main structs needed by users
-pub use parser::parse_dsl_input;
+pub use structs::{Value, Date, DateTime, DslRoot, Table, Row, HeaderField, TableData}; // Export main structs needed by users
+pub use parser::{parse_dsl_input, parse_dsl_input_with_options, ParseOptions};
 pub use parser::DslStatement;
+pub use parse_error::{ParseError, ParseErrors};
 pub use query::{execute_query, execute_update, execute_add, execute_pack};
 
 
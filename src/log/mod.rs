@@ -1,18 +1,277 @@
 // src/log/mod.rs
 
-// 占位符，后续将根据 architecture.md 实现
-// 日志模块 (LogMgr) - 隐含但重要
+// 日志模块 (LogMgr) - 预写日志 (WAL)
+//
+// 每条记录在磁盘上的格式为：
+//   [payload 长度: u32 LE][payload 的 CRC32: u32 LE][payload: JSON 编码的 LogRecord]
+// 崩溃可能留下写了一半的尾部记录，recover() 遇到长度或校验和不匹配的记录时停止，
+// 并把文件截断到最后一条完整记录处，之后的追加写入不会受到影响。
+//
+// 事务的写入在提交时才与它的 Commit 记录一起追加 (见 log_batch)，检查点不需要等待尚未提交的事务。
+// 保存点只影响提交前的写集，不写入日志。
 
-pub struct LogMgr;
+use crate::error::Error;
+use crate::storage::{unix_millis, StorageEngine};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const RECORD_HEADER_LEN: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum LogRecord {
+    Begin { transaction_id: u64 },
+    Write {
+        transaction_id: u64,
+        key: String,
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires_at: Option<u64>, // 过期时间 (Unix 毫秒)
+    },
+    Delete { transaction_id: u64, key: String },
+    Commit {
+        transaction_id: u64,
+        commit_ts: u64,
+        // 提交时的墙上时间 (Unix 毫秒)，重放时恢复到版本上；旧的日志中没有，使用重放时的时间
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        committed_at: Option<u64>,
+    },
+    Rollback { transaction_id: u64 },
+}
+
+impl LogRecord {
+    pub fn transaction_id(&self) -> u64 {
+        match self {
+            LogRecord::Begin { transaction_id }
+            | LogRecord::Write { transaction_id, .. }
+            | LogRecord::Delete { transaction_id, .. }
+            | LogRecord::Commit { transaction_id, .. }
+            | LogRecord::Rollback { transaction_id } => *transaction_id,
+        }
+    }
+}
+
+pub struct LogMgr {
+    path: PathBuf,
+    file: File,
+}
 
 impl LogMgr {
-    pub fn log_operation(transaction_id: u64, operation_type: String, data: String) {
-        // TODO: 实现记录操作逻辑
-        println!("Logging operation for transaction ID: {}, type: {}, data: {} in LogMgr...", transaction_id, operation_type, data);
+    // 打开 (或创建) 日志文件，新记录总是追加到文件末尾
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        println!("LogMgr: Opening write-ahead log '{}'", path.display());
+        let file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+        Ok(LogMgr { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 当前日志文件大小 (字节)，用于决定何时做检查点
+    pub fn len(&self) -> Result<u64, Error> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+
+    // 追加一条记录；不会 fsync，需要持久化时调用 sync()
+    pub fn log_operation(&mut self, record: &LogRecord) -> Result<(), Error> {
+        println!("LogMgr: Logging {:?}", record);
+        let mut frames = Vec::new();
+        encode_record(record, &mut frames)?;
+        Ok(self.file.write_all(&frames)?)
+    }
+
+    // 用一次写入追加一组记录 (一个事务的全部写入和它的 Commit 记录)；不会 fsync
+    // 崩溃时最多留下这组记录的一个前缀，没有 Commit 记录的事务在恢复时被丢弃
+    pub fn log_batch(&mut self, records: &[LogRecord]) -> Result<(), Error> {
+        println!("LogMgr: Logging batch of {} record(s)", records.len());
+        let mut frames = Vec::new();
+        for record in records {
+            encode_record(record, &mut frames)?;
+        }
+        Ok(self.file.write_all(&frames)?)
+    }
+
+    // 将已追加的记录刷到磁盘 (提交时调用)
+    pub fn sync(&mut self) -> Result<(), Error> {
+        Ok(self.file.sync_data()?)
+    }
+
+    // 检查点完成后清空日志
+    pub fn truncate(&mut self) -> Result<(), Error> {
+        println!("LogMgr: Truncating write-ahead log '{}'", self.path.display());
+        self.file.set_len(0)?;
+        Ok(self.file.sync_all()?)
     }
 
-    pub fn recover() {
-        // TODO: 实现系统启动时进行恢复逻辑
-        println!("Recovering system in LogMgr...");
+    // 读取日志中所有完整且校验通过的记录，并截掉损坏的尾部
+    pub fn read_records(&mut self) -> Result<Vec<LogRecord>, Error> {
+        let mut content = Vec::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut content)?;
+
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset + RECORD_HEADER_LEN <= content.len() {
+            let len = u32::from_le_bytes(content[offset..offset + 4].try_into().unwrap()) as usize;
+            let checksum = u32::from_le_bytes(content[offset + 4..offset + 8].try_into().unwrap());
+            let payload_start = offset + RECORD_HEADER_LEN;
+            if payload_start + len > content.len() {
+                break; // 写了一半的记录
+            }
+            let payload = &content[payload_start..payload_start + len];
+            if crc32(payload) != checksum {
+                break;
+            }
+            match serde_json::from_slice::<LogRecord>(payload) {
+                Ok(record) => records.push(record),
+                Err(_) => break,
+            }
+            offset = payload_start + len;
+        }
+
+        if offset < content.len() {
+            println!(
+                "LogMgr: Discarding {} bytes of torn or corrupted log tail in '{}'",
+                content.len() - offset,
+                self.path.display()
+            );
+            self.file.set_len(offset as u64)?;
+            self.file.sync_all()?;
+        }
+        Ok(records)
+    }
+
+    // 系统启动时进行恢复：按提交顺序重放已提交事务的写入，丢弃未提交的事务
+    // 提交时间戳不大于数据文件中 last_commit_ts 的事务已经包含在数据文件中，会被跳过，因此重放是幂等的
+    // 返回重放的事务数量
+    pub fn recover(&mut self, storage_engine: &mut StorageEngine) -> Result<usize, Error> {
+        println!("LogMgr: Recovering from '{}'...", self.path.display());
+        let mut in_flight: HashMap<u64, Vec<LogRecord>> = HashMap::new();
+        let mut replayed = 0;
+
+        for record in self.read_records()? {
+            storage_engine.observe_transaction_id(record.transaction_id());
+            match record {
+                LogRecord::Begin { transaction_id } => {
+                    in_flight.insert(transaction_id, Vec::new());
+                }
+                LogRecord::Write { transaction_id, .. } | LogRecord::Delete { transaction_id, .. } => {
+                    in_flight.entry(transaction_id).or_default().push(record);
+                }
+                LogRecord::Commit { transaction_id, commit_ts, committed_at } => {
+                    let ops = in_flight.remove(&transaction_id).unwrap_or_default();
+                    if commit_ts <= storage_engine.last_commit_ts() {
+                        continue;
+                    }
+                    // 同一事务的所有写入在一个提交时间戳下重放，同一个键以最后一次写入为准
+                    let mut writes = HashMap::new();
+                    for op in ops {
+                        match op {
+                            LogRecord::Write { key, value, expires_at, .. } => writes.insert(key, (Some(value), expires_at)),
+                            LogRecord::Delete { key, .. } => writes.insert(key, (None, None)),
+                            _ => None,
+                        };
+                    }
+                    let writes = writes.into_iter().map(|(key, (value, expires_at))| (key, value, expires_at));
+                    storage_engine.commit_expiring_writes_at_time(writes, commit_ts, committed_at.unwrap_or_else(unix_millis));
+                    storage_engine.record_transaction_commit(transaction_id, commit_ts);
+                    replayed += 1;
+                }
+                LogRecord::Rollback { transaction_id } => {
+                    in_flight.remove(&transaction_id);
+                }
+            }
+        }
+
+        if !in_flight.is_empty() {
+            println!("LogMgr: Discarded {} uncommitted transaction(s) during recovery", in_flight.len());
+        }
+        println!("LogMgr: Recovery replayed {} committed transaction(s)", replayed);
+        Ok(replayed)
+    }
+}
+
+// 把一条记录编码为 [长度][CRC32][payload] 追加到 frames
+fn encode_record(record: &LogRecord, frames: &mut Vec<u8>) -> Result<(), Error> {
+    let payload = serde_json::to_vec(record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    frames.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frames.extend_from_slice(&crc32(&payload).to_le_bytes());
+    frames.extend_from_slice(&payload);
+    Ok(())
+}
+
+// CRC-32 (IEEE 802.3)，用于检测损坏的日志记录
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::temp_data_dir;
+
+    fn write_txn(log: &mut LogMgr, transaction_id: u64, key: &str, value: &str, commit: bool) {
+        log.log_operation(&LogRecord::Begin { transaction_id }).unwrap();
+        log.log_operation(&LogRecord::Write { transaction_id, key: key.to_string(), value: value.to_string(), expires_at: None }).unwrap();
+        if commit {
+            // 测试中每个事务使用自己的 ID 作为提交时间戳和提交时间
+            log.log_operation(&LogRecord::Commit { transaction_id, commit_ts: transaction_id, committed_at: Some(transaction_id) })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_recover_skips_uncommitted_and_torn_records() {
+        let dir = temp_data_dir("wal_torn");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("wal.log");
+        {
+            let mut log = LogMgr::open(&path).unwrap();
+            write_txn(&mut log, 1, "committed", "v1", true);
+            write_txn(&mut log, 2, "uncommitted", "v2", false);
+            write_txn(&mut log, 3, "torn", "v3", true);
+            log.sync().unwrap();
+        }
+        // 模拟崩溃：最后一条 Commit 记录只写了一半
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 5).unwrap();
+
+        let mut log = LogMgr::open(&path).unwrap();
+        let mut engine = StorageEngine::new();
+        assert_eq!(log.recover(&mut engine).unwrap(), 1);
+        assert_eq!(engine.read(&"committed".to_string()), Some("v1".to_string()));
+        assert_eq!(engine.read(&"uncommitted".to_string()), None);
+        assert_eq!(engine.read(&"torn".to_string()), None);
+        assert_eq!(engine.transaction_commit_ts(1), Some(1));
+        assert_eq!(engine.transaction_commit_ts(2), None);
+        assert_eq!(engine.history(&"committed".to_string())[0].committed_at, 1);
+
+        // 损坏的尾部被截掉后，新的记录可以正常追加并被恢复
+        write_txn(&mut log, 4, "after_crash", "v4", true);
+        let mut engine = StorageEngine::new();
+        assert_eq!(log.recover(&mut engine).unwrap(), 2);
+        assert_eq!(engine.read(&"after_crash".to_string()), Some("v4".to_string()));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
-}
\ No newline at end of file
+}
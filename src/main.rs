@@ -373,12 +373,12 @@ pack user config project_config assembly cloned_table other_original_table anoth
                                                                 match &original_table_for_key.data {
                                                                     skdb::TableData::Indexed(map) => {
                                                                         if let Some(first_key) = map.keys().next() {
-                                                                            key_to_query = Some(format!("{}", first_key));
+                                                                            key_to_query = Some(first_key.to_string());
                                                                         }
                                                                     }
                                                                     skdb::TableData::GroupedIndexed(map) => {
                                                                          if let Some(first_key) = map.keys().next() {
-                                                                            key_to_query = Some(format!("{}", first_key));
+                                                                            key_to_query = Some(first_key.to_string());
                                                                         }
                                                                     }
                                                                     _ => {}
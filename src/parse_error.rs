@@ -0,0 +1,139 @@
+use std::fmt;
+
+// --- DSL Parse Errors ---
+
+// A problem found while parsing DSL input, located by line and column (both 1-based).
+// `text` is the offending part of the line and `source_line` the whole line, so the
+// error can be rendered with a caret under the problem without the original input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub message: String,
+    pub source_line: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, text: &str, source_line: &str, message: impl Into<String>) -> Self {
+        ParseError {
+            file: None,
+            line,
+            column,
+            text: text.to_string(),
+            message: message.into(),
+            source_line: source_line.to_string(),
+        }
+    }
+
+    fn location(&self) -> String {
+        format!("{}:{}:{}", self.file.as_deref().unwrap_or("<input>"), self.line, self.column)
+    }
+
+    // Renders the error compiler-style:
+    //
+    //   error: Data line has more fields (3) than headers (2)
+    //     --> users.hs:12:9
+    //      |
+    //   12 | 1, foo, extra
+    //      |         ^^^^^
+    pub fn render(&self) -> String {
+        let gutter = " ".repeat(self.line.to_string().len());
+        // Keep tabs in the padding so the caret lines up with the source line
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(self.text.chars().count().max(1));
+        format!(
+            "error: {}\n{}--> {}\n{} |\n{} | {}\n{} | {}{}",
+            self.message,
+            gutter,
+            self.location(),
+            gutter,
+            self.line,
+            self.source_line,
+            gutter,
+            padding,
+            carets
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location(), self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// All errors found in one parse, in source order. The parser keeps going after an
+// error so that a single run reports every bad statement and row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseErrors {
+    pub errors: Vec<ParseError>,
+}
+
+impl ParseErrors {
+    // Attributes every error to `file` (parse_dsl_input only sees the text)
+    pub fn with_file(mut self, file: &str) -> Self {
+        for error in &mut self.errors {
+            error.file = Some(file.to_string());
+        }
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ParseError> {
+        self.errors.iter()
+    }
+
+    // Renders every error with its source line and a caret, followed by a summary line
+    pub fn render(&self) -> String {
+        let mut rendered: Vec<String> = self.errors.iter().map(ParseError::render).collect();
+        rendered.push(format!("{} parse error(s)", self.errors.len()));
+        rendered.join("\n\n")
+    }
+}
+
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self.errors.iter().map(ParseError::to_string).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for ParseErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_points_at_the_offending_text() {
+        let error = ParseError::new(12, 9, "extra", "1, foo, extra", "Data line has more fields (3) than headers (2)");
+        let errors = ParseErrors { errors: vec![error] }.with_file("users.hs");
+        assert_eq!(
+            errors.errors[0].render(),
+            "error: Data line has more fields (3) than headers (2)\n  --> users.hs:12:9\n   |\n12 | 1, foo, extra\n   |         ^^^^^"
+        );
+        assert_eq!(errors.to_string(), "users.hs:12:9: Data line has more fields (3) than headers (2)");
+    }
+
+    #[test]
+    fn test_render_keeps_tabs_in_the_caret_padding() {
+        let error = ParseError::new(1, 3, "x", "\t\tx", "bad");
+        assert!(error.render().ends_with("\n  | \t\t^"));
+    }
+}
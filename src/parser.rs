@@ -1,5 +1,10 @@
-use std::collections::HashMap;
-use crate::structs::{Value, HeaderField, Row, TableData, Table}; 
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use crate::lexer::{is_word_char, quote, tokenize, unescape, Token, TokenKind};
+use crate::parse_error::{ParseError, ParseErrors};
+use crate::query::value_type_to_string;
+use crate::structs::{Value, Date, DateTime, HeaderField, Row, TableData, Table}; 
 
 #[derive(Debug, PartialEq)]
 pub enum DslStatement {
@@ -19,325 +24,604 @@ pub enum DslStatement {
     Pack { table_names: Vec<String> },
 }
 
-// Helper to parse arguments for pack, expecting "table1 table2 ..."
-fn parse_pack_args(args_line: &str) -> Result<DslStatement, String> {
-    let command_content = args_line.split('#').next().unwrap_or("").trim(); 
+// --- Recursive-descent parser for the .hs format ---
+//
+// Works on the tokens from crate::lexer; the grammar is documented in docs/hs-grammar.md and
+// each parse_* function below implements the rule quoted in its comment. After an error the
+// parser resumes at the next line, so a single run reports every bad statement and row.
+//
+// In strict mode (ParseOptions::strict, or a "strict" line in the file) tables are also checked
+// against their headers: see parse_table.
 
-    if command_content.is_empty() {
-        return Err("Pack arguments must specify at least one table name.".to_string());
-    }
+// Options for parse_dsl_input_with_options
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParseOptions {
+    // Check every table in the file as if the file started with a "strict" line
+    pub strict: bool,
+}
+
+// The input text, used to slice token spans and to build errors that quote the source line
+struct Source<'a> {
+    input: &'a str,
+    lines: Vec<&'a str>,
+}
 
-    let table_names: Vec<String> = command_content
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect();
+impl<'a> Source<'a> {
+    fn new(input: &'a str) -> Self {
+        let lines = input.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+        Source { input, lines }
+    }
 
-    if table_names.is_empty() || table_names.iter().any(|name| name.is_empty()) {
-        return Err("Table names in pack statement cannot be empty or missing.".to_string());
+    // The text covered by a run of tokens from one line, including the whitespace between them
+    fn text(&self, tokens: &[Token]) -> &'a str {
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => &self.input[first.start..last.end],
+            _ => "",
+        }
     }
 
-    Ok(DslStatement::Pack { table_names })
+    // An error pointing at `tokens`, which must not be empty
+    fn error(&self, tokens: &[Token], message: impl Into<String>) -> ParseError {
+        let first = &tokens[0];
+        ParseError::new(first.line, first.column, self.text(tokens), self.lines[first.line - 1], message)
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Directive {
+    Update,
+    Pack,
+    Add,
+    From,
+    Strict,
+}
 
-pub fn parse_dsl_input(input: &str, _mock_fs: Option<&HashMap<String, String>>) -> Result<Vec<DslStatement>, String> {
-    let mut statements = Vec::new();
-    let mut current_block_lines: Vec<String> = Vec::new();
-    let mut in_block = false;
+// Directives are recognized by their leading tokens; every other line belongs to a table
+fn directive_kind(line: &[Token]) -> Option<Directive> {
+    let first = &line[0];
+    let second = line.get(1);
+    if first.kind == TokenKind::Hash {
+        Some(Directive::Update)
+    } else if first.kind != TokenKind::Word {
+        None
+    } else if first.text == "strict" && second.is_none() {
+        Some(Directive::Strict)
+    } else if first.text == "pack" && second.is_none_or(|token| token.kind == TokenKind::Word) {
+        Some(Directive::Pack)
+    } else if first.text.starts_with('.') && !first.text.starts_with("..") {
+        Some(Directive::Add)
+    } else if second.is_some_and(|token| token.kind == TokenKind::Word && token.text == "from")
+        && !line.iter().any(|token| token.kind == TokenKind::Colon)
+    {
+        Some(Directive::From)
+    } else {
+        None
+    }
+}
 
-    for line_with_ending in input.lines() {
-        let line = line_with_ending.trim_end_matches(['\r', '\n']); 
-        let trimmed_line = line.trim(); 
+struct Parser<'s, 'a> {
+    source: &'s Source<'a>,
+    lines: Vec<&'s [Token<'a>]>, // Non-empty lines, without their Newline tokens
+    pos: usize,
+    errors: Vec<ParseError>,
+    strict: bool,
+    table_names: HashSet<String>,                // Tables defined or imported by the file
+    custom_column_types: Vec<(String, ParseError)>, // Strict mode: column types that must name one of them
+}
 
-        if trimmed_line.is_empty() {
-            continue;
-        }
-        
-        if trimmed_line.starts_with("##") { // Double hash for full line comment
-            continue;
-        }
-        if trimmed_line.starts_with('#') { // Any other line starting with # is a comment
-            continue; 
+impl<'s, 'a> Parser<'s, 'a> {
+    fn new(source: &'s Source<'a>, tokens: &'s [Token<'a>], options: ParseOptions) -> Self {
+        let lines = tokens
+            .split(|token| matches!(token.kind, TokenKind::Newline | TokenKind::Eof))
+            .filter(|line| !line.is_empty())
+            .collect();
+        Parser {
+            source,
+            lines,
+            pos: 0,
+            errors: Vec::new(),
+            strict: options.strict,
+            table_names: HashSet::new(),
+            custom_column_types: Vec::new(),
         }
+    }
 
-        let mut processed_as_statement = false;
+    fn peek_line(&self) -> Option<&'s [Token<'a>]> {
+        self.lines.get(self.pos).copied()
+    }
 
-        // 1. Check for known keyword-prefixed directives/operations first
-        if trimmed_line.starts_with("pack ") {
-            if let Some(args_part) = trimmed_line.strip_prefix("pack ").map(|s| s.trim_start()) {
-                if in_block && !current_block_lines.is_empty() {
-                    let block_str = current_block_lines.join("\n");
-                    if !block_str.trim().is_empty() { statements.push(DslStatement::Definition(parse_block(&block_str)?.0, parse_block(&block_str)?.1));}
-                    current_block_lines.clear(); in_block = false;
+    // file = { directive | table | "~" }
+    fn parse_file(&mut self) -> Vec<DslStatement> {
+        let mut statements = Vec::new();
+        while let Some(line) = self.peek_line() {
+            let statement = match directive_kind(line) {
+                Some(directive) => {
+                    self.pos += 1;
+                    let statement = match directive {
+                        Directive::Update => self.parse_update(line),
+                        Directive::Pack => self.parse_pack(line),
+                        Directive::Add => self.parse_add(line),
+                        Directive::From => self.parse_from(line),
+                        Directive::Strict => {
+                            // strict = "strict"      (checks the tables after it)
+                            self.strict = true;
+                            continue;
+                        }
+                    };
+                    if let Ok(
+                        DslStatement::CopyStructure { source_table_name, target_table_name, .. }
+                        | DslStatement::Reference { source_table_name, target_table_name, .. },
+                    ) = &statement
+                    {
+                        self.table_names.extend([source_table_name.clone(), target_table_name.clone()]);
+                    }
+                    statement.map_err(|error| self.errors.push(error)).ok()
                 }
-                statements.push(parse_pack_args(args_part)?);
-                processed_as_statement = true;
-            }
-        } else if trimmed_line.starts_with("#.") { 
-            if in_block && !current_block_lines.is_empty() {
-                let block_str = current_block_lines.join("\n");
-                if !block_str.trim().is_empty() { statements.push(DslStatement::Definition(parse_block(&block_str)?.0, parse_block(&block_str)?.1));}
-                current_block_lines.clear(); in_block = false;
-            }
-            statements.push(parse_update_statement(trimmed_line)?);
-            processed_as_statement = true;
-        } else if trimmed_line.starts_with('.') && !trimmed_line.starts_with("..") { 
-             if in_block && !current_block_lines.is_empty() {
-                let block_str = current_block_lines.join("\n");
-                 if !block_str.trim().is_empty() { statements.push(DslStatement::Definition(parse_block(&block_str)?.0, parse_block(&block_str)?.1));}
-                current_block_lines.clear(); in_block = false;
+                None if line[0].kind == TokenKind::Tilde => {
+                    // A "~" outside a table ends an empty block and is ignored
+                    self.pos += 1;
+                    if line.len() > 1 {
+                        self.errors.push(self.source.error(&line[1..], "Unexpected text after '~'"));
+                    }
+                    None
+                }
+                None => self.parse_table(),
+            };
+            statements.extend(statement);
+        }
+
+        // Tables can be used as column types before they are defined
+        for (type_name, error) in std::mem::take(&mut self.custom_column_types) {
+            if !self.table_names.contains(&type_name) {
+                self.errors.push(error);
             }
-            statements.push(parse_add_statement(trimmed_line)?);
-            processed_as_statement = true;
         }
+        statements
+    }
 
-        if processed_as_statement {
-            continue;
+    // update = "#." path "=" value      (path and value are the text on either side of the first '=')
+    //
+    // The '=' can be part of a WORD (e.g. "f=1"); one inside a STRING does not count.
+    fn parse_update(&self, line: &[Token]) -> Result<DslStatement, ParseError> {
+        // The lexer only produces a Hash right before a '.', so line[1] is a WORD starting with it
+        let target = &line[1..];
+        let equals = target
+            .iter()
+            .filter(|token| token.kind == TokenKind::Word)
+            .find_map(|token| token.text.find('=').map(|offset| token.start + offset));
+        let Some(equals) = equals else {
+            return Err(self.source.error(line, "Invalid update statement format, expected '#.path = value'"));
+        };
+        let input = self.source.input;
+        let path = input[target[0].start + 1..equals].trim();
+        if path.is_empty() {
+            return Err(self.source.error(line, "Update path cannot be empty"));
         }
+        let value_str = input[equals + 1..line[line.len() - 1].end].trim();
+        Ok(DslStatement::Update { path: path.to_string(), value_str: value_str.to_string() })
+    }
 
-        // 2. Try to parse as "keyword-less" (pattern-based) directives if not a table definition start
-        // A table definition must start with "name:", so if a line doesn't contain ":" it might be a keyword-less directive.
-        // Or, more robustly, check for " from " and " as " patterns.
-        let parts: Vec<&str> = trimmed_line.split_whitespace().collect();
-        let mut parsed_as_keywordless_directive = false;
-
-        if !trimmed_line.contains(':') { // Heuristic: table names usually end with ':' on their line
-            if parts.len() == 5 && parts[1] == "from" && parts[3] == "as" {
-                // Potential CopyStructure: source_table from "path" as target_table
-                let source_table_name = parts[0].to_string();
-                let source_path = parts[2].trim_matches('"').to_string();
-                let target_table_name = parts[4].to_string();
-                if !source_table_name.is_empty() && !source_path.is_empty() && !target_table_name.is_empty() && !source_table_name.contains(':') {
-                    if in_block && !current_block_lines.is_empty() {
-                        let block_str = current_block_lines.join("\n");
-                        if !block_str.trim().is_empty() { statements.push(DslStatement::Definition(parse_block(&block_str)?.0, parse_block(&block_str)?.1));}
-                        current_block_lines.clear(); in_block = false;
-                    }
-                    statements.push(DslStatement::CopyStructure{ source_table_name, source_path, target_table_name });
-                    parsed_as_keywordless_directive = true;
-                }
-            } else if parts.len() == 3 && parts[1] == "from" {
-                // Potential Reference: source_table from "path"
-                let source_table_name = parts[0].to_string();
-                let source_path = parts[2].trim_matches('"').to_string();
-                if !source_table_name.is_empty() && !source_path.is_empty() && !source_table_name.contains(':') {
-                     if in_block && !current_block_lines.is_empty() {
-                        let block_str = current_block_lines.join("\n");
-                        if !block_str.trim().is_empty() { statements.push(DslStatement::Definition(parse_block(&block_str)?.0, parse_block(&block_str)?.1));}
-                        current_block_lines.clear(); in_block = false;
-                    }
-                    statements.push(DslStatement::Reference{ source_table_name: source_table_name.clone(), source_path, target_table_name: source_table_name });
-                    parsed_as_keywordless_directive = true;
-                }
-            }
+    // pack = "pack" WORD { WORD }
+    fn parse_pack(&self, line: &[Token]) -> Result<DslStatement, ParseError> {
+        let args = &line[1..];
+        if args.is_empty() {
+            return Err(self.source.error(line, "Pack arguments must specify at least one table name"));
+        }
+        if let Some(bad) = args.iter().position(|token| token.kind != TokenKind::Word) {
+            return Err(self.source.error(&args[bad..=bad], "Expected a table name"));
         }
+        Ok(DslStatement::Pack { table_names: args.iter().map(|token| token.text.to_string()).collect() })
+    }
 
-        if parsed_as_keywordless_directive {
-            continue;
+    // add = "." NAME ".add" "(" ")"      (a single WORD followed by the parentheses)
+    fn parse_add(&self, line: &[Token]) -> Result<DslStatement, ParseError> {
+        let target = &line[0];
+        let is_call = line.len() == 3 && line[1].kind == TokenKind::LParen && line[2].kind == TokenKind::RParen;
+        let Some(table_name) = target.text.strip_suffix(".add").filter(|_| is_call) else {
+            return Err(self.source.error(line, "Invalid add statement format, expected '.table_name.add()'"));
+        };
+        let table_name = table_name.get(1..).unwrap_or("");
+        if table_name.is_empty() {
+            return Err(self.source.error(line, "Add statement is missing a table name"));
         }
+        if table_name.contains(['.', '[', ']']) {
+            let source_line = self.source.lines[target.line - 1];
+            return Err(ParseError::new(target.line, target.column + 1, table_name, source_line, "Invalid table name for add operation, must be a simple name"));
+        }
+        Ok(DslStatement::Add { table_name: table_name.to_string() })
+    }
 
-        // 3. If none of the above, assume it's part of a table definition block
-        current_block_lines.push(line.to_string()); 
-        in_block = true;
+    // copy      = WORD "from" path "as" WORD
+    // reference = WORD "from" path
+    // path      = STRING | WORD
+    fn parse_from(&self, line: &[Token]) -> Result<DslStatement, ParseError> {
+        let source_table_name = line[0].text.to_string();
+        let Some(path) = line.get(2) else {
+            return Err(self.source.error(&line[1..2], "Expected a file path after 'from'"));
+        };
+        let source_path = match path.kind {
+            TokenKind::Str => &path.text[1..path.text.len() - 1],
+            TokenKind::Word => path.text,
+            _ => return Err(self.source.error(&line[2..3], "File path must be a quoted string")),
+        };
+        if source_path.is_empty() {
+            return Err(self.source.error(&line[2..3], "File path cannot be empty"));
+        }
+        let source_path = source_path.to_string();
 
-        if line.contains('~') {
-            let parts_before_tilde: Vec<&str> = line.splitn(2, '~').collect();
-            let content_for_current_block = parts_before_tilde[0].trim_end(); 
-            
-            if !current_block_lines.is_empty() { current_block_lines.pop(); }
-            if !content_for_current_block.is_empty() { current_block_lines.push(content_for_current_block.to_string()); }
+        match &line[3..] {
+            [] => Ok(DslStatement::Reference { target_table_name: source_table_name.clone(), source_table_name, source_path }),
+            [keyword, target] if keyword.kind == TokenKind::Word && keyword.text == "as" && target.kind == TokenKind::Word => {
+                Ok(DslStatement::CopyStructure { source_table_name, source_path, target_table_name: target.text.to_string() })
+            }
+            rest => Err(self.source.error(rest, "Expected 'as target_table' or the end of the line")),
+        }
+    }
 
-            if !current_block_lines.is_empty() {
-                let block_str = current_block_lines.join("\n");
-                 if !block_str.trim().is_empty() { statements.push(DslStatement::Definition(parse_block(&block_str)?.0, parse_block(&block_str)?.1));}
+    // table = name ":" NEWLINE [ header NEWLINE ] { row NEWLINE } [ "~" ]
+    //
+    // The table is only produced when all of it is valid. Without a header line the rows are
+    // kept unparsed (TableData::RawLines), e.g. for tables whose structure comes from a copy.
+    //
+    // In strict mode a table with a header is also rejected for unknown column types,
+    // duplicate column names, cells that do not fit their column's type, rows with fewer
+    // fields than the header and repeated `index` keys.
+    fn parse_table(&mut self) -> Option<DslStatement> {
+        let name_line = self.peek_line()?;
+        self.pos += 1;
+        let table_name = self.parse_table_name(name_line);
+        if let Ok(name) = &table_name {
+            self.table_names.insert(name.clone());
+        }
+        let header = match self.peek_line() {
+            Some(line) if line[0].kind == TokenKind::Slash => {
+                self.pos += 1;
+                Some(self.parse_header(line))
             }
-            current_block_lines.clear(); in_block = false;
-
-            if parts_before_tilde.len() > 1 {
-                let content_after_tilde = parts_before_tilde[1].trim();
-                if !content_after_tilde.is_empty() {
-                    // This could be the start of a new block or a new keyword-less directive.
-                    // Re-feed to a simplified parsing logic or assume it's a new block start.
-                    // For simplicity, assume it's a new block start if it contains ':'.
-                    // This part is tricky and might need a lookahead or a more formal grammar.
-                    // For now, if it's not empty, push it to start a new block.
-                    current_block_lines.push(content_after_tilde.to_string());
-                    in_block = true; 
-                }
+            _ => None,
+        };
+        let rows = self.parse_rows();
+
+        let table_name = table_name.map_err(|error| self.errors.push(error)).ok()?;
+        let table = match header {
+            None => Table {
+                name: table_name.clone(),
+                headers: Vec::new(),
+                header_map: HashMap::new(),
+                data: TableData::RawLines(rows.iter().map(|row| self.source.text(row).to_string()).collect()),
+                primary_key_field_name: None,
+            },
+            Some(header) => {
+                let (headers, primary_key_field_name) = header.map_err(|error| self.errors.push(error)).ok()?;
+                self.parse_table_data(&table_name, headers, primary_key_field_name, &rows)?
             }
-        }
+        };
+        Some(DslStatement::Definition(table_name, table))
     }
 
-    if in_block && !current_block_lines.is_empty() {
-        let block_str = current_block_lines.join("\n");
-        if !block_str.trim().is_empty() { statements.push(DslStatement::Definition(parse_block(&block_str)?.0, parse_block(&block_str)?.1));}
+    // name = WORD { WORD }
+    fn parse_table_name(&self, line: &[Token]) -> Result<String, ParseError> {
+        match line.iter().position(|token| token.kind == TokenKind::Colon) {
+            Some(colon) if colon > 0 && colon + 1 == line.len() && line[..colon].iter().all(|token| token.kind == TokenKind::Word) => {
+                Ok(self.source.text(&line[..colon]).to_string())
+            }
+            _ => Err(self.source.error(line, "Invalid table name format, expected 'name:'")),
+        }
     }
 
-    Ok(statements)
-}
+    // header = "/" field "/" { field "/" }
+    // field  = WORD { WORD } [ ( "::" | ":" ) type ]
+    // type   = WORD { "::" WORD }
+    //
+    // A single ':' is the older spelling of '::' (e.g. "/id:index/"). The first field typed
+    // index, sindex or gindex is the table's primary key.
+    fn parse_header(&mut self, line: &[Token]) -> Result<(Vec<HeaderField>, Option<String>), ParseError> {
+        if line.len() == 1 {
+            return Err(self.source.error(line, "Header line does not define any fields"));
+        }
+        let is = |i: usize, kind: TokenKind| line.get(i).is_some_and(|token| token.kind == kind);
 
+        let mut headers: Vec<HeaderField> = Vec::new();
+        let mut primary_key_field_name: Option<String> = None;
+        let mut i = 1; // line[0] is the opening '/'
+        while i < line.len() {
+            let name_start = i;
+            while is(i, TokenKind::Word) {
+                i += 1;
+            }
+            if i == name_start {
+                return Err(self.source.error(&line[i..=i], "Header field name cannot be empty"));
+            }
+            let name_tokens = &line[name_start..i];
+            let name = self.source.text(name_tokens).to_string();
 
-fn parse_update_statement(line: &str) -> Result<DslStatement, String> {
-    let parts: Vec<&str> = line.splitn(2, '=').map(|s| s.trim()).collect();
-    if parts.len() != 2 {
-        return Err(format!("Invalid update statement format: '{}'. Expected '#.path = value'", line));
-    }
-    let path = parts[0].strip_prefix("#.").ok_or_else(|| format!("Update path missing '#.' prefix: '{}'", parts[0]))?.to_string();
-    if path.is_empty() {
-        return Err(format!("Update path cannot be empty in: '{}'", line));
-    }
-    let value_str = parts[1].to_string();
+            let mut type_info = None;
+            if is(i, TokenKind::DoubleColon) || is(i, TokenKind::Colon) {
+                let type_start = i + 1;
+                loop {
+                    if !is(i + 1, TokenKind::Word) {
+                        return Err(self.source.error(&line[i..=i], format!("Expected a type after '{}'", line[i].text)));
+                    }
+                    i += 2;
+                    if !is(i, TokenKind::DoubleColon) {
+                        break;
+                    }
+                }
+                let type_name = self.source.text(&line[type_start..i]).to_string();
+                if self.strict && !is_builtin_type(&type_name) {
+                    let error = self.source.error(&line[type_start..i], format!("Unknown column type '{}'", type_name));
+                    self.custom_column_types.push((type_name.clone(), error));
+                }
+                type_info = Some(type_name);
+            }
+            if self.strict && headers.iter().any(|header| header.name == name) {
+                self.errors.push(self.source.error(name_tokens, format!("Duplicate column name '{}'", name)));
+            }
 
-    Ok(DslStatement::Update { path, value_str })
-}
+            let is_primary_key = matches!(type_info.as_deref(), Some("index" | "sindex" | "gindex")) && primary_key_field_name.is_none();
+            if is_primary_key {
+                primary_key_field_name = Some(name.clone());
+            }
+            headers.push(HeaderField { name, type_info, is_primary_key });
 
-fn parse_add_statement(line: &str) -> Result<DslStatement, String> {
-    let trimmed_line = line.trim();
-    if !trimmed_line.ends_with(".add()") {
-        return Err(format!("Invalid add statement format: '{}'. Expected '.table_name.add()'", line));
+            match line.get(i) {
+                Some(token) if token.kind == TokenKind::Slash => i += 1,
+                Some(_) => return Err(self.source.error(&line[i..], "Expected '/' after header field")),
+                None => return Err(self.source.error(&line[i - 1..i], "Header line must end with '/'")),
+            }
+        }
+        Ok((headers, primary_key_field_name))
     }
-    
-    let table_name_part = trimmed_line.trim_end_matches(".add()").strip_prefix('.')
-        .ok_or_else(|| format!("Invalid table name for add operation: '{}'", line))?;
-    
-    if table_name_part.is_empty() || table_name_part.contains('.') || table_name_part.contains('[') || table_name_part.contains(']') {
-        return Err(format!("Invalid table name for add operation: '{}'. Must be a simple name.", table_name_part));
+
+    // Rows run until a "~" line, a directive or the end of the input. A row may end with the
+    // "~" itself; anything after a "~" on the same line is an error.
+    fn parse_rows(&mut self) -> Vec<&'s [Token<'a>]> {
+        let mut rows = Vec::new();
+        while let Some(line) = self.peek_line() {
+            if directive_kind(line).is_some() {
+                break;
+            }
+            self.pos += 1;
+            let Some(tilde) = line.iter().position(|token| token.kind == TokenKind::Tilde) else {
+                rows.push(line);
+                continue;
+            };
+            if tilde > 0 {
+                rows.push(&line[..tilde]);
+            }
+            if tilde + 1 < line.len() {
+                self.errors.push(self.source.error(&line[tilde + 1..], "Unexpected text after '~'"));
+            }
+            break;
+        }
+        rows
     }
 
-    Ok(DslStatement::Add { table_name: table_name_part.to_string() })
-}
+    // Parses the rows of a table with a header, reporting every bad row
+    fn parse_table_data(
+        &mut self,
+        table_name: &str,
+        headers: Vec<HeaderField>,
+        primary_key_field_name: Option<String>,
+        rows: &[&[Token]],
+    ) -> Option<Table> {
+        let mut header_map = HashMap::new();
+        for (i, h) in headers.iter().enumerate() {
+            header_map.insert(h.name.clone(), i);
+        }
 
+        let primary_key = primary_key_field_name.as_ref().and_then(|pk_name| {
+            let index = headers.iter().position(|h| h.name == *pk_name && h.is_primary_key)?;
+            Some((index, headers[index].type_info.as_deref()))
+        });
 
-fn parse_block(block_str: &str) -> Result<(String, Table), String> {
-    let lines: Vec<&str> = block_str.lines()
-                                    .map(|s| s.trim())
-                                    .filter(|s| !s.is_empty() && !s.starts_with("##")) 
-                                    .collect();
-    if lines.is_empty() {
-        return Err("Block is empty or only contains comments".to_string());
-    }
-
-    let name_line_full = lines[0];
-    if name_line_full.trim_start().starts_with('#') { // A line starting with # should have been skipped by parse_dsl_input
-        return Err(format!("Table definition block cannot start with a comment line that was not filtered: '{}'", name_line_full));
-    }
-    let name_line_no_comment = name_line_full.split('#').next().unwrap_or("").split("//").next().unwrap_or("").trim();
-    let table_name = name_line_no_comment.strip_suffix(':').ok_or_else(|| format!("Invalid table name format: '{}'. Expected 'name:' for line '{}'", name_line_no_comment, name_line_full))?.to_string();
-    
-    let mut is_header_line_present = false;
-    if lines.len() >= 2 {
-        let potential_header_line_full = lines[1];
-        let line_for_header_check = if potential_header_line_full.trim_start().starts_with('#') {
-            "" 
-        } else {
-            potential_header_line_full
-        };
-        let potential_header_line_no_comment = line_for_header_check.split('#').next().unwrap_or("").split("//").next().unwrap_or("").trim();
-        if potential_header_line_no_comment.starts_with('/') && potential_header_line_no_comment.ends_with('/') {
-            is_header_line_present = true;
+        let errors_before = self.errors.len();
+        let mut data_rows: Vec<Row> = Vec::new();
+        let mut indexed_data: HashMap<String, Row> = HashMap::new();
+        let mut grouped_data: HashMap<String, Vec<Row>> = HashMap::new();
+        let mut key_lines: HashMap<String, usize> = HashMap::new(); // Strict mode: where each index key was first used
+        for &row_tokens in rows {
+            let (row, cells) = match parse_row(self.source, row_tokens, &headers, self.strict) {
+                Ok(parsed) => parsed,
+                Err(errors) => {
+                    self.errors.extend(errors);
+                    continue;
+                }
+            };
+
+            match primary_key {
+                Some((index, Some(kind @ ("index" | "gindex")))) => {
+                    let pk_value = &row.fields[&headers[index].name];
+                    let cell = cells.get(index).map_or(row_tokens, |cell| &row_tokens[cell.clone()]);
+                    let key_span = if cell.is_empty() { row_tokens } else { cell };
+                    let key = match value_to_string_key(pk_value) {
+                        Ok(key) => key,
+                        Err(e) => {
+                            self.errors.push(self.source.error(key_span, format!("Invalid primary key for table '{}': {}", table_name, e)));
+                            continue;
+                        }
+                    };
+                    if kind == "index" && self.strict {
+                        match key_lines.entry(key.clone()) {
+                            Entry::Occupied(first) => {
+                                let message = format!("Duplicate key '{}' in table '{}', first used on line {}", key, table_name, first.get());
+                                self.errors.push(self.source.error(key_span, message));
+                                continue;
+                            }
+                            Entry::Vacant(entry) => {
+                                entry.insert(row_tokens[0].line);
+                            }
+                        }
+                    }
+                    if kind == "index" {
+                        indexed_data.insert(key, row);
+                    } else {
+                        grouped_data.entry(key).or_default().push(row);
+                    }
+                }
+                _ => data_rows.push(row), // "sindex" and untyped keys keep insertion order
+            }
+        }
+        if self.errors.len() > errors_before {
+            return None;
         }
-    }
 
-    if !is_header_line_present {
-        let raw_data_lines: Vec<String> = if lines.len() > 1 {
-            lines.iter().skip(1)
-                 .map(|s| s.split('#').next().unwrap_or("").split("//").next().unwrap_or("").trim()) 
-                 .filter(|s| !s.is_empty()) 
-                 .map(|s| s.to_string())
-                 .collect()
-        } else {
-            Vec::new() 
+        let table_data = match primary_key {
+            Some((_, Some("index"))) => TableData::Indexed(indexed_data),
+            Some((_, Some("gindex"))) => TableData::GroupedIndexed(grouped_data),
+            _ => TableData::Sequential(data_rows),
         };
 
-        return Ok((
-            table_name.clone(),
-            Table {
-                name: table_name,
-                headers: Vec::new(), 
-                header_map: HashMap::new(),
-                data: TableData::RawLines(raw_data_lines),
-                primary_key_field_name: None, 
-            },
-        ));
-    }
-
-    let header_line_full = lines[1];
-    let header_str_no_comment = header_line_full.split('#').next().unwrap_or("").split("//").next().unwrap_or("").trim();
-    let (headers, primary_key_field_name_from_header) = parse_header_line(header_str_no_comment)?;
-    
-    let mut header_map = HashMap::new();
-    for (i, h) in headers.iter().enumerate() {
-        header_map.insert(h.name.clone(), i);
+        Some(Table {
+            name: table_name.to_string(),
+            headers,
+            header_map,
+            data: table_data,
+            primary_key_field_name,
+        })
     }
+}
 
-    let mut data_rows: Vec<Row> = Vec::new();
-    for data_line_full in lines.iter().skip(2) {
-        let data_line_content = data_line_full.split('#').next().unwrap_or("").split("//").next().unwrap_or("").trim();
-        
-        if data_line_content.is_empty() { 
-            continue;
+// Splits tokens on the commas outside parentheses, returning the token range of each part.
+// "a,b," has three parts, the last one empty.
+fn split_commas(source: &Source, tokens: &[Token]) -> Result<Vec<Range<usize>>, ParseError> {
+    let mut parts = Vec::new();
+    let mut open_parens = Vec::new();
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::LParen => open_parens.push(i),
+            // The guard pops the matching '(' when there is one
+            TokenKind::RParen if open_parens.pop().is_none() => {
+                return Err(source.error(&tokens[i..=i], "Unmatched ')'"));
+            }
+            TokenKind::Comma if open_parens.is_empty() => {
+                parts.push(start..i);
+                start = i + 1;
+            }
+            _ => {}
         }
-        data_rows.push(parse_data_line(data_line_content, &headers, &header_map)?);
     }
-    
-    let table_data;
-    let final_primary_key_field_name = primary_key_field_name_from_header.clone();
+    if let Some(&i) = open_parens.last() {
+        return Err(source.error(&tokens[i..=i], "Unclosed '('"));
+    }
+    parts.push(start..tokens.len());
+    Ok(parts)
+}
 
-    if let Some(pk_name) = &final_primary_key_field_name {
-        let pk_header_opt = headers.iter().find(|h| h.name == *pk_name && h.is_primary_key);
-        if pk_header_opt.is_none() {
-            return Err(format!("Primary key field '{}' (from id:type notation) not found or not marked as PK in header fields for table '{}'", pk_name, table_name));
-        }
+// row = cell { "," cell }
+//
+// Returns the row and the token range of every cell, or every error in the row. Headers
+// without a cell get the value of an empty cell, unless `strict` is set: then a short row is
+// an error, and so is a cell whose value does not fit its column (see column_accepts).
+fn parse_row(source: &Source, row: &[Token], headers: &[HeaderField], strict: bool) -> Result<(Row, Vec<Range<usize>>), Vec<ParseError>> {
+    let cells = split_commas(source, row).map_err(|error| vec![error])?;
+    if cells.len() > headers.len() && !headers.is_empty() { // headers.len() > 0 to allow schemaless tables
+        // Point at the extra fields, or at the trailing comma if they are empty
+        let extra = &cells[headers.len()];
+        let from = if extra.is_empty() { extra.start - 1 } else { extra.start };
+        return Err(vec![source.error(&row[from..], format!("Data line has more fields ({}) than headers ({})", cells.len(), headers.len()))]);
+    }
 
-        let pk_type_info = pk_header_opt.unwrap().type_info.as_deref();
-        match pk_type_info {
-            Some("index") => {
-                let mut indexed_data = HashMap::new();
-                for row in data_rows {
-                    if let Some(pk_value) = row.fields.get(pk_name) {
-                        indexed_data.insert(value_to_string_key(pk_value)?, row);
-                    } else {
-                        return Err(format!("Primary key field '{}' not found in a data row for indexed table '{}'", pk_name, table_name));
-                    }
+    let mut errors = Vec::new();
+    if strict && cells.len() < headers.len() {
+        errors.push(source.error(row, format!("Data line has fewer fields ({}) than headers ({})", cells.len(), headers.len())));
+    }
+    let mut fields = HashMap::new();
+    for (i, header) in headers.iter().enumerate() {
+        let cell = cells.get(i).map_or(&[][..], |cell| &row[cell.clone()]);
+        let type_info = header.type_info.as_deref();
+        match parse_cell(source, cell, type_info, strict) {
+            Ok(value) => {
+                // Missing cells have been reported above
+                if let Some(type_info) = type_info.filter(|t| strict && i < cells.len() && !column_accepts(t, &value)) {
+                    let span = if cell.is_empty() { row } else { cell };
+                    let message = format!("Column '{}' is {}, found {}", header.name, type_info, value_type_to_string(&value));
+                    errors.push(source.error(span, message));
                 }
-                table_data = TableData::Indexed(indexed_data);
+                fields.insert(header.name.clone(), value);
             }
-            Some("gindex") => {
-                let mut grouped_data = HashMap::new();
-                for row in data_rows {
-                    if let Some(pk_value) = row.fields.get(pk_name) {
-                        grouped_data.entry(value_to_string_key(pk_value)?).or_insert_with(Vec::new).push(row);
-                    } else {
-                         return Err(format!("Primary key field '{}' not found in a data row for gindexed table '{}'", pk_name, table_name));
-                    }
-                }
-                table_data = TableData::GroupedIndexed(grouped_data);
+            Err(error) => errors.push(error),
+        }
+    }
+    if errors.is_empty() {
+        Ok((Row { fields }, cells))
+    } else {
+        Err(errors)
+    }
+}
+
+// cell  = STRING | tuple | { token }
+// tuple = "(" [ cell { "," cell } ] ")"
+//
+// A quoted string is always a String, whatever the column type. Tuple elements are parsed
+// without a type; any other cell is interpreted from its text by parse_scalar, which fails
+// for text that does not fit a boolean, float, date or datetime column (or, in strict mode,
+// an integer column).
+fn parse_cell(source: &Source, tokens: &[Token], type_info: Option<&str>, strict: bool) -> Result<Value, ParseError> {
+    let scalar = || parse_scalar(source.text(tokens), type_info, strict).map_err(|message| source.error(tokens, message));
+    match tokens {
+        [token] if token.kind == TokenKind::Str => Ok(Value::String(token.string_value())),
+        [open, inner @ .., close] if open.kind == TokenKind::LParen && close.kind == TokenKind::RParen => {
+            if inner.is_empty() {
+                return Ok(Value::Tuple(Vec::new()));
             }
-            Some("sindex") | _ => { 
-                table_data = TableData::Sequential(data_rows);
+            // "(a)(b)" starts and ends with parentheses but is not a tuple
+            match split_commas(source, inner) {
+                Ok(elements) => elements
+                    .into_iter()
+                    .map(|element| parse_cell(source, &inner[element], None, strict))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Tuple),
+                Err(_) => scalar(),
             }
         }
+        _ => scalar(),
+    }
+}
+
+// Parses the whole input, collecting every error instead of stopping at the first one.
+// Statements that fail to parse are left out; the result is Err if there was any error.
+pub fn parse_dsl_input(input: &str, _mock_fs: Option<&HashMap<String, String>>) -> Result<Vec<DslStatement>, ParseErrors> {
+    parse_dsl_input_with_options(input, ParseOptions::default())
+}
+
+// parse_dsl_input with options, e.g. to check a file in strict mode without a "strict" line
+pub fn parse_dsl_input_with_options(input: &str, options: ParseOptions) -> Result<Vec<DslStatement>, ParseErrors> {
+    let source = Source::new(input);
+    let (tokens, mut errors) = tokenize(input);
+    let mut parser = Parser::new(&source, &tokens, options);
+    let statements = parser.parse_file();
+
+    errors.extend(parser.errors);
+    if errors.is_empty() {
+        Ok(statements)
     } else {
-        if headers.iter().any(|h| h.type_info.as_deref() == Some("sindex")) {
-            table_data = TableData::Sequential(data_rows);
-        } else {
-            table_data = TableData::Sequential(data_rows);
-        }
+        errors.sort_by_key(|error| (error.line, error.column));
+        Err(ParseErrors { errors })
     }
+}
 
-    Ok((table_name.clone(), Table {
-        name: table_name,
-        headers,
-        header_map,
-        data: table_data,
-        primary_key_field_name: final_primary_key_field_name,
-    }))
+// Parses a single data row (e.g. a RawLines line) against `headers`
+pub fn parse_data_line(line_str: &str, headers: &[HeaderField], _header_map: &HashMap<String, usize>) -> Result<Row, String> {
+    let (tokens, lex_errors) = tokenize(line_str);
+    if let Some(error) = lex_errors.first() {
+        return Err(format!("{}: '{}'", error.message, line_str));
+    }
+    let row: Vec<Token> = tokens.into_iter().filter(|token| !matches!(token.kind, TokenKind::Newline | TokenKind::Eof)).collect();
+    parse_row(&Source::new(line_str), &row, headers, false)
+        .map(|(row, _)| row)
+        .map_err(|errors| format!("{}: '{}'", errors[0].message, line_str))
 }
 
 pub fn value_to_string_key(value: &Value) -> Result<String, String> { 
     match value {
         Value::String(s) => Ok(s.clone()),
         Value::Integer(i) => Ok(i.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Float(f) => Ok(format!("{:?}", f)),
+        Value::Date(date) => Ok(date.to_string()),
+        Value::DateTime(datetime) => Ok(datetime.to_string()),
         Value::Tuple(vals) => {
             let inner_keys: Result<Vec<String>, String> = vals.iter().map(|v| {
                  value_to_string_key(v)
@@ -350,220 +634,145 @@ pub fn value_to_string_key(value: &Value) -> Result<String, String> {
     }
 }
 
-fn parse_header_line(line: &str) -> Result<(Vec<HeaderField>, Option<String>), String> {
-    if !line.starts_with('/') || !line.ends_with('/') {
-        return Err(format!("Header line must start and end with '/': {}", line));
-    }
-    let inner = &line[1..line.len() - 1];
-    if inner.is_empty() && line == "/" { 
-        return Ok((Vec::new(), None));
-    }
-    if inner.is_empty() && line == "//" { 
-        return Ok((Vec::new(), None));
-    }
-
 
-    let mut headers = Vec::new();
-    let mut primary_key_field_name: Option<String> = None;
-
-    for part_str in inner.split('/') {
-        if part_str.is_empty() { continue; } 
-
-        let parts: Vec<&str> = part_str.splitn(2, "::").collect();
-        let name = parts[0].trim().to_string();
-        if name.is_empty() {
-            return Err(format!("Header field name cannot be empty in part: '{}'", part_str));
-        }
-
-        let mut type_info_str: Option<String> = None;
-        let mut is_pk_from_type = false;
-
-        if parts.len() == 2 {
-            let type_part = parts[1].trim();
-            if !type_part.is_empty() {
-                type_info_str = Some(type_part.to_string());
-                if (type_part == "index" || type_part == "sindex" || type_part == "gindex") && primary_key_field_name.is_none() {
-                    primary_key_field_name = Some(name.clone());
-                    is_pk_from_type = true;
-                }
-            }
-        }
-        
-        headers.push(HeaderField {
-            name,
-            type_info: type_info_str,
-            is_primary_key: is_pk_from_type, 
-        });
-    }
-    if headers.is_empty() && !inner.is_empty() {
-         return Err(format!("Header line parsed to empty headers, but was not empty: '{}'", line));
+// Parses a value written in the cell syntax, e.g. the right-hand side of an update.
+// Fails like a cell in a data row would: on a bad string literal, or on text that does not
+// fit a boolean, float, date or datetime column.
+pub fn try_parse_value_str(s: &str, field_type_info: Option<&str>) -> Result<Value, String> {
+    let (tokens, errors) = tokenize(s);
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error.message);
     }
+    let tokens: Vec<Token> = tokens.into_iter().filter(|token| !matches!(token.kind, TokenKind::Newline | TokenKind::Eof)).collect();
+    parse_cell(&Source::new(s), &tokens, field_type_info, false).map_err(|error| error.message)
+}
 
-    Ok((headers, primary_key_field_name))
+// Like try_parse_value_str, but keeps text that does not parse as a String
+pub fn parse_value_str(s: &str, field_type_info: Option<&str>) -> Value {
+    try_parse_value_str(s, field_type_info).unwrap_or_else(|e| {
+        eprintln!("[WARN] Failed to parse '{}': {}, treating as string.", s.trim(), e);
+        Value::String(s.trim().to_string())
+    })
 }
 
-pub fn parse_data_line(line_str: &str, headers: &[HeaderField], _header_map: &HashMap<String, usize>) -> Result<Row, String> {
-    let mut parts = Vec::new();
-    let mut current_part = String::new();
-    let mut in_quotes = false;
-    let mut parentheses_level = 0; // 新增：跟踪括号层级
-    for char_code in line_str.chars() {
-        match char_code {
-            '"' => {
-                in_quotes = !in_quotes;
-                current_part.push(char_code);
-            }
-            '(' if !in_quotes => { // 新增：处理开括号
-                parentheses_level += 1;
-                current_part.push(char_code);
-            }
-            ')' if !in_quotes => { // 新增：处理闭括号
-                if parentheses_level > 0 {
-                    parentheses_level -= 1;
-                }
-                current_part.push(char_code);
-            }
-            ',' if !in_quotes && parentheses_level == 0 => { // 修改：增加括号层级判断
-                parts.push(current_part.trim().to_string());
-                current_part.clear();
-            }
-            _ => {
-                current_part.push(char_code);
-            }
-        }
+// Writes a string in the cell syntax: bare when it reads back as the same string, otherwise
+// as a quoted literal. parse_value_str(&string_literal(s), None) is always String(s).
+pub fn string_literal(s: &str) -> String {
+    let is_bare = !s.is_empty()
+        && s.chars().all(|c| is_word_char(c) && !matches!(c, '"' | '\'' | '\\'))
+        && !s.starts_with('.') // Would start an add statement at the beginning of a row
+        && !matches!(s, "pack" | "strict") // Would read as a directive on a line of its own
+        && parse_scalar(s, None, false) == Ok(Value::String(s.to_string()));
+    if is_bare {
+        s.to_string()
+    } else {
+        quote(s)
     }
-    parts.push(current_part.trim().to_string()); // Add the last part
+}
 
-    if parts.len() > headers.len() && headers.len() > 0 { // headers.len() > 0 to allow schemaless tables
-        return Err(format!("Data line has more parts ({}) than headers ({}): '{}'", parts.len(), headers.len(), line_str));
-    }
-    
-    // If parts are fewer than headers, it might be due to trailing empty fields not captured by simple split.
-    // The original code implicitly handled this by iterating up to parts.len() and then filling missing headers.
-    // We need to ensure this behavior is preserved or correctly adapted.
-    // The new splitting logic should correctly capture empty strings if they are meant to be fields.
-    // For example, "a,,c" should result in ["a", "", "c"].
-    // The current logic for `parts.push(current_part.trim().to_string());` might need adjustment if `current_part` is empty.
-    // Let's test: if line is "a," -> parts: ["a", ""]. If "a,b," -> parts: ["a", "b", ""]. This seems correct.
+// Floats written in plain decimal form, e.g. "3.14", "-0.5" or "6.02e23". Untyped cells need a
+// '.' or an exponent to be floats, so "42" stays an integer.
+fn parse_decimal_float(s: &str) -> Option<f64> {
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let is_float = digits(whole)
+        && fraction.is_none_or(digits)
+        && exponent.is_none_or(digits)
+        && (fraction.is_some() || exponent.is_some());
+    if is_float { s.parse().ok() } else { None }
+}
 
-    let mut fields = HashMap::new();
-    for (i, part_str) in parts.iter().enumerate() {
-        if i < headers.len() {
-            let header = &headers[i];
-            // parse_value_str will handle unquoting if the part_str is a quoted string
-            let value = parse_value_str(part_str, header.type_info.as_deref());
-            fields.insert(header.name.clone(), value);
-        }
-    }
-    
-    // Ensure all headers have a corresponding field, even if it's an empty string (or Null based on type)
-    // This loop is crucial for data lines with fewer fields than headers.
-    for (i, header) in headers.iter().enumerate() {
-        if i >= parts.len() { // If a header doesn't have a corresponding part from the split
-            if !fields.contains_key(&header.name) { // And it wasn't somehow added (shouldn't happen with i >= parts.len())
-                 // Default to empty string, parse_value_str might convert to Null if appropriate for type
-                fields.insert(header.name.clone(), parse_value_str("", header.type_info.as_deref()));
-            }
-        }
+// Column types whose cells must parse as their type; an empty cell is Null. In strict mode
+// integer columns are checked too.
+fn is_strict_type(type_info: &str, strict: bool) -> bool {
+    match type_info.to_lowercase().as_str() {
+        "boolean" | "float" | "date" | "datetime" => true,
+        "integer" => strict,
+        _ => false,
     }
+}
 
-    Ok(Row { fields })
+// Column types that need no table of that name
+fn is_builtin_type(type_info: &str) -> bool {
+    type_info.contains("::")
+        || matches!(
+            type_info.to_lowercase().as_str(),
+            "integer" | "string" | "boolean" | "float" | "date" | "datetime" | "index" | "sindex" | "gindex"
+        )
 }
 
-// Helper function to split elements of a tuple string, respecting quotes and parentheses
-fn split_tuple_elements(tuple_content: &str) -> Vec<String> {
-    let mut elements = Vec::new();
-    if tuple_content.is_empty() { // Handle empty tuple "()"
-        return elements;
-    }
-    let mut current_element = String::new();
-    let mut in_quotes = false;
-    let mut parentheses_level = 0;
-
-    for char_code in tuple_content.chars() {
-        match char_code {
-            '"' => {
-                in_quotes = !in_quotes;
-                current_element.push(char_code);
-            }
-            '(' if !in_quotes => {
-                parentheses_level += 1;
-                current_element.push(char_code);
-            }
-            ')' if !in_quotes => {
-                if parentheses_level > 0 {
-                    parentheses_level -= 1;
-                }
-                current_element.push(char_code);
-            }
-            ',' if !in_quotes && parentheses_level == 0 => {
-                elements.push(current_element.trim().to_string());
-                current_element.clear();
-            }
-            _ => {
-                current_element.push(char_code);
-            }
-        }
+// Whether a parsed cell fits its column in strict mode. Unquoted text has already been
+// checked by parse_scalar, so this catches quoted strings and tuples in typed columns.
+// Null fits every column; `index`, `gindex` and table-typed columns take any value.
+fn column_accepts(type_info: &str, value: &Value) -> bool {
+    match (type_info.to_lowercase().as_str(), value) {
+        (_, Value::Null)
+        | ("integer" | "sindex", Value::Integer(_))
+        | ("boolean", Value::Bool(_))
+        | ("float", Value::Float(_))
+        | ("date", Value::Date(_))
+        | ("datetime", Value::DateTime(_))
+        | ("string", Value::String(_)) => true,
+        ("integer" | "sindex" | "boolean" | "float" | "date" | "datetime" | "string", _) => false,
+        _ => true,
     }
-    // Add the last part. Handles cases like "(a)", "(a,b)", also if tuple_content was non-empty but resulted in no splits.
-    elements.push(current_element.trim().to_string());
-    elements
 }
 
-pub fn parse_value_str(s: &str, field_type_info: Option<&str>) -> Value {
+// Interprets the text of a cell that is neither a quoted string nor a tuple
+fn parse_scalar(s: &str, field_type_info: Option<&str>, strict: bool) -> Result<Value, String> {
     let trimmed_s = s.trim();
 
     if trimmed_s.is_empty() {
-        // For consistency, if field_type_info suggests Null is possible for empty,
-        // this could be Value::Null. But current parser returns Value::String("").
-        // Let's keep Value::String("") for empty.
-        return Value::String("".to_string());
+        // Untyped and legacy-typed columns keep Value::String("") for empty cells
+        if field_type_info.is_some_and(|t| is_strict_type(t, strict)) {
+            return Ok(Value::Null);
+        }
+        return Ok(Value::String("".to_string()));
     }
 
-    // Handle "null" string, converting to Value::Null unless type is string
+    // Bare "null" is Null in every column, string columns included; the string "null" is
+    // written quoted, which string_literal already does
     if trimmed_s.eq_ignore_ascii_case("null") {
-        if field_type_info.map_or(true, |t| t.to_lowercase() != "string") {
-            return Value::Null;
-        }
-        // If type is "string", then "null" is the string "null"
-    }
-
-    // Attempt to parse as a tuple if it looks like one: ("elem1", "elem2", ...)
-    if trimmed_s.starts_with('(') && trimmed_s.ends_with(')') && trimmed_s.len() >= 2 {
-        let inner_content = &trimmed_s[1..trimmed_s.len() - 1];
-        let elements_str = split_tuple_elements(inner_content);
-        let mut values = Vec::new();
-        for elem_s in elements_str {
-            // Recursively parse each element. Pass None for type_info,
-            // so it auto-detects or defaults to string for tuple elements.
-            values.push(parse_value_str(&elem_s, None));
-        }
-        return Value::Tuple(values);
+        return Ok(Value::Null);
     }
 
     if let Some(type_info) = field_type_info {
         match type_info.to_lowercase().as_str() { // Normalize type_info for comparison
             "integer" => {
                 if let Ok(i) = trimmed_s.parse::<i64>() {
-                    return Value::Integer(i);
+                    return Ok(Value::Integer(i));
+                } else if strict {
+                    return Err(format!("Invalid integer '{}'", trimmed_s));
                 } else {
                     eprintln!("[WARN] Failed to parse '{}' as integer, treating as string.", trimmed_s);
-                    // Fall through to default string parsing if parse fails
+                    return Ok(Value::String(trimmed_s.to_string()));
                 }
             }
             "boolean" => {
                 if trimmed_s.eq_ignore_ascii_case("true") {
-                    return Value::String("true".to_string());
+                    return Ok(Value::Bool(true));
                 } else if trimmed_s.eq_ignore_ascii_case("false") {
-                    return Value::String("false".to_string());
-                } else {
-                    eprintln!("[WARN] Failed to parse '{}' as boolean, treating as string.", trimmed_s);
-                    // Fall through
+                    return Ok(Value::Bool(false));
                 }
+                return Err(format!("Invalid boolean '{}', expected true or false", trimmed_s));
+            }
+            "float" => {
+                // Also accepts "inf" and "NaN", which untyped cells read as strings
+                return trimmed_s.parse::<f64>().map(Value::Float).map_err(|_| format!("Invalid float '{}'", trimmed_s));
             }
+            "date" => return trimmed_s.parse::<Date>().map(Value::Date),
+            "datetime" => return trimmed_s.parse::<DateTime>().map(Value::DateTime),
             "string" => {
-                // If type is explicitly string, return the trimmed string as is (quotes included if present).
-                return Value::String(trimmed_s.to_string());
+                // If type is explicitly string, return the trimmed text as is ("123" stays a string).
+                return Ok(Value::String(trimmed_s.to_string()));
             }
             // Handle reference types like "type_name::key_value_string"
             // This check should be specific enough not to misinterpret other type_info.
@@ -584,20 +793,20 @@ pub fn parse_value_str(s: &str, field_type_info: Option<&str>) -> Value {
                      let key_val = if let Ok(i) = key_val_str.parse::<i64>() {
                          Box::new(Value::Integer(i))
                      } else {
-                         let unquoted_key_val_str = if key_val_str.starts_with('"') && key_val_str.ends_with('"') && key_val_str.len() >= 2 {
-                             key_val_str[1..key_val_str.len()-1].to_string()
+                         let is_quoted = key_val_str.len() >= 2
+                             && ((key_val_str.starts_with('"') && key_val_str.ends_with('"')) || (key_val_str.starts_with('\'') && key_val_str.ends_with('\'')));
+                         let unquoted_key_val_str = if is_quoted {
+                             unescape(&key_val_str[1..key_val_str.len()-1])
                          } else {
                              key_val_str.to_string()
                          };
                          Box::new(Value::String(unquoted_key_val_str))
                      };
-                     return Value::Reference { type_name: ref_type_name, key: key_val };
+                     return Ok(Value::Reference { type_name: ref_type_name, key: key_val });
                  }
                  // If format is not "Type::Key" but type_info had "::", warn and fall through.
                  eprintln!("[WARN] Value '{}' with type_info '{}' expected format Type::Key for reference, treating as string.", trimmed_s, type_info);
             }
-            // Add other known types like "date", "datetime" if they have specific parsing.
-            // For now, they will fall through.
             _ => { /* Unknown type_info, fall through to default parsing */ }
         }
     }
@@ -605,24 +814,317 @@ pub fn parse_value_str(s: &str, field_type_info: Option<&str>) -> Value {
     // Fallback parsing (if not a tuple, and no specific type_info matched or type_info was None)
     // This is also where elements from a tuple (parsed with type_info=None) will land.
 
-    // 1. Try to parse as integer
+    // 1. Try to parse as integer, then as a decimal float
     if let Ok(i) = trimmed_s.parse::<i64>() {
-        return Value::Integer(i);
+        return Ok(Value::Integer(i));
+    }
+    if let Some(f) = parse_decimal_float(trimmed_s) {
+        return Ok(Value::Float(f));
     }
 
     // 2. Try to parse as boolean "true" or "false" (case-insensitive)
     if trimmed_s.eq_ignore_ascii_case("true") {
-        return Value::String("true".to_string()); // Consistent with "boolean" type_info parsing
+        return Ok(Value::Bool(true)); // Consistent with "boolean" type_info parsing
     }
     if trimmed_s.eq_ignore_ascii_case("false") {
-        return Value::String("false".to_string()); // Consistent with "boolean" type_info parsing
+        return Ok(Value::Bool(false)); // Consistent with "boolean" type_info parsing
+    }
+
+    // 3. Default to string as is (quoted strings never get here). Dates are only recognized
+    // in date and datetime columns, so untyped "2025-12-31" stays a string.
+    Ok(Value::String(trimmed_s.to_string()))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::execute_pack;
+    use crate::structs::DslRoot;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::{select, Index};
+
+    #[test]
+    fn test_parse_indexed_table() {
+        let statements = parse_dsl_input("user:\n/id::index/name/\n1,alice # first\n2,\"bob # not a comment\"\n~", None).unwrap();
+        let [DslStatement::Definition(name, table)] = statements.as_slice() else {
+            panic!("expected a single definition, got {:?}", statements);
+        };
+        assert_eq!(name, "user");
+        assert_eq!(table.primary_key_field_name.as_deref(), Some("id"));
+        let TableData::Indexed(rows) = &table.data else { panic!("expected indexed data") };
+        assert_eq!(rows["1"].fields["name"], Value::String("alice".to_string()));
+        assert_eq!(rows["2"].fields["name"], Value::String("bob # not a comment".to_string()));
+    }
+
+    #[test]
+    fn test_directives_and_terminators() {
+        let input = "# comment\n~\nt:\n/a:index/b/\n1,x ~\ndept from \"src/dept.hs\"\nproj from \"p.hs\" as active\nraw:\nq, r\n.t.add()\npack t raw\n";
+        let statements = parse_dsl_input(input, None).unwrap();
+        assert_eq!(statements.len(), 6);
+        let DslStatement::Definition(_, table) = &statements[0] else { panic!("expected a definition") };
+        assert_eq!(table.primary_key_field_name.as_deref(), Some("a")); // "a:index" is the older spelling of "a::index"
+        assert!(matches!(&statements[1], DslStatement::Reference { source_path, .. } if source_path == "src/dept.hs"));
+        assert!(matches!(&statements[2], DslStatement::CopyStructure { target_table_name, .. } if target_table_name == "active"));
+        let DslStatement::Definition(_, raw) = &statements[3] else { panic!("expected a definition") };
+        assert_eq!(raw.data, TableData::RawLines(vec!["q, r".to_string()]));
+        assert_eq!(statements[4], DslStatement::Add { table_name: "t".to_string() });
+        assert_eq!(statements[5], DslStatement::Pack { table_names: vec!["t".to_string(), "raw".to_string()] });
+    }
+
+    #[test]
+    fn test_update_statements() {
+        let statements = parse_dsl_input("t:\n/f/\n0\n#.t[0].f = 1\n#.t[0].f='a = b' // note\n#.t[1].g=\n", None).unwrap();
+        let update = |path: &str, value_str: &str| DslStatement::Update { path: path.to_string(), value_str: value_str.to_string() };
+        assert!(matches!(&statements[0], DslStatement::Definition(name, _) if name == "t"));
+        assert_eq!(statements[1..], [update("t[0].f", "1"), update("t[0].f", "'a = b'"), update("t[1].g", "")]);
+
+        let errors = parse_dsl_input("#.t[0].f\n#. = 1\n", None).unwrap_err().errors;
+        let messages: Vec<(usize, &str)> = errors.iter().map(|e| (e.line, e.message.as_str())).collect();
+        assert_eq!(messages, vec![(1, "Invalid update statement format, expected '#.path = value'"), (2, "Update path cannot be empty")]);
+    }
+
+    #[test]
+    fn test_errors_are_collected_with_positions() {
+        let input = "user:\n/id::index/name/\n1,alice\n  2, bob, extra\nnull,carol\n(3,dan\n~\n.a.b.add()\npack # nothing\nx from\n";
+        let errors = parse_dsl_input(input, None).unwrap_err().errors;
+        let positions: Vec<(usize, usize, &str)> =
+            errors.iter().map(|e| (e.line, e.column, e.text.as_str())).collect();
+        assert_eq!(
+            positions,
+            vec![(4, 11, "extra"), (5, 1, "null"), (6, 1, "("), (8, 2, "a.b"), (9, 1, "pack"), (10, 3, "from")]
+        );
+        assert_eq!(errors[0].source_line, "  2, bob, extra");
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let input = "t:\n/a/b::string/c/\n'it\\'s',\"say \\\"hi\\\"\\n\\u{1F600}\",(\"x,y\",'')\n\"two\nlines\",null,O'Brien\n3,\"null\",\n";
+        let statements = parse_dsl_input(input, None).unwrap();
+        let [DslStatement::Definition(_, table)] = statements.as_slice() else { panic!("expected a definition") };
+        let TableData::Sequential(rows) = &table.data else { panic!("expected sequential data") };
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(rows[0].fields["a"], string("it's"));
+        assert_eq!(rows[0].fields["b"], string("say \"hi\"\n😀"));
+        assert_eq!(rows[0].fields["c"], Value::Tuple(vec![string("x,y"), string("")]));
+        assert_eq!(rows[1].fields["a"], string("two\nlines"));
+        assert_eq!(rows[1].fields["b"], Value::Null);
+        assert_eq!(rows[2].fields["b"], string("null"));
+        assert_eq!(rows[1].fields["c"], string("O'Brien"));
+
+        let errors = parse_dsl_input("t:\n/a/\n\"bad \\q\"\n'open\n", None).unwrap_err().errors;
+        let positions: Vec<(usize, usize, &str)> =
+            errors.iter().map(|e| (e.line, e.column, e.text.as_str())).collect();
+        assert_eq!(positions, vec![(3, 6, "\\q"), (4, 1, "'open")]);
+    }
+
+    #[test]
+    fn test_typed_columns() {
+        let input = "t:\n/ok::boolean/price::float/day::date/at::datetime/any/\nTRUE,3,2024-02-29,2024-02-29T23:59:00,2.50\nfalse,-1.5e3,,2025-01-01 00:00:00,true\n";
+        let statements = parse_dsl_input(input, None).unwrap();
+        let [DslStatement::Definition(_, table)] = statements.as_slice() else { panic!("expected a definition") };
+        let TableData::Sequential(rows) = &table.data else { panic!("expected sequential data") };
+        let day = Date { year: 2024, month: 2, day: 29 };
+        assert_eq!(rows[0].fields["ok"], Value::Bool(true));
+        assert_eq!(rows[0].fields["price"], Value::Float(3.0));
+        assert_eq!(rows[0].fields["day"], Value::Date(day));
+        assert_eq!(rows[0].fields["at"], Value::DateTime(DateTime { date: day, hour: 23, minute: 59, second: 0 }));
+        assert_eq!(rows[0].fields["any"], Value::Float(2.5));
+        assert_eq!(rows[1].fields["price"], Value::Float(-1500.0));
+        assert_eq!(rows[1].fields["day"], Value::Null);
+        assert_eq!(rows[1].fields["any"], Value::Bool(true));
+
+        let input = "t:\n/ok::boolean/price::float/day::date/at::datetime/\nyes,1,2023-02-29,2025-01-01\n";
+        let errors = parse_dsl_input(input, None).unwrap_err().errors;
+        assert_eq!((errors[0].line, errors[0].column, errors[0].text.as_str()), (3, 1, "yes"));
+        assert_eq!(parse_value_str("1,5", Some("float")), Value::String("1,5".to_string()));
+        assert!(try_parse_value_str("2023-02-29", Some("date")).unwrap_err().contains("day is out of range"));
+        assert!(try_parse_value_str("2025-01-01", Some("datetime")).is_err());
+    }
+
+    #[test]
+    fn test_text_after_tilde_is_an_error() {
+        let errors = parse_dsl_input("a:\n/x/\n1\n~ oops\n~\n~ b:", None).unwrap_err().errors;
+        let positions: Vec<(usize, usize, &str)> =
+            errors.iter().map(|e| (e.line, e.column, e.text.as_str())).collect();
+        assert_eq!(positions, vec![(4, 3, "oops"), (6, 3, "b:")]);
+    }
+
+    #[test]
+    fn test_strict_mode_reports_every_violation() {
+        let tables = "t:\n/id::index/n::integer/n/kind::nosuch/ok::boolean/\na,1,x,k,true\na,2,y,k,false\nb,zz,y,k,true\nc,\"5\",y,k,'no'\nd,3\n~\nu:\n/d::later/\n1\n~\nlater:\n/a/\n";
+        assert!(parse_dsl_input(tables, None).is_ok());
+
+        let errors = parse_dsl_input(&format!("strict\n{}", tables), None).unwrap_err().errors;
+        let positions: Vec<(usize, usize, &str)> =
+            errors.iter().map(|e| (e.line, e.column, e.text.as_str())).collect();
+        assert_eq!(
+            positions,
+            vec![(3, 23, "n"), (3, 31, "nosuch"), (5, 1, "a"), (6, 3, "zz"), (7, 3, "\"5\""), (7, 11, "'no'"), (8, 1, "d,3")]
+        );
+        assert_eq!(errors[2].message, "Duplicate key 'a' in table 't', first used on line 4");
+        assert_eq!(errors[4].message, "Column 'n' is integer, found String");
+        assert_eq!(errors[6].message, "Data line has fewer fields (2) than headers (5)");
+
+        let strict = ParseOptions { strict: true };
+        assert_eq!(parse_dsl_input_with_options(tables, strict).unwrap_err().errors.len(), 7);
+        let statements = parse_dsl_input_with_options("t:\n/id::sindex/n::integer/\n0,\n1,null\n", strict).unwrap();
+        let [DslStatement::Definition(_, table)] = statements.as_slice() else { panic!("expected a definition") };
+        let TableData::Sequential(rows) = &table.data else { panic!("expected sequential data") };
+        assert_eq!((&rows[0].fields["n"], &rows[1].fields["n"]), (&Value::Null, &Value::Null));
+        assert_eq!(string_literal("strict"), "\"strict\"");
+    }
+
+    // Strings built from pieces that are awkward for the format: delimiters, comments, quotes,
+    // escapes, line breaks, and text that would otherwise read back as another value
+    static STRING_PIECES: [&str; 25] = [
+        "a", "Zé", "数据", "😀", " ", ",", "(", ")", "'", "\"", "\\", "\n", "\r\n", "\t", "\u{1}", "#", "//", "~",
+        "::", "/", "null", "123", "pack", "strict", ".x",
+    ];
+
+    fn string_strategy() -> impl Strategy<Value = String> {
+        vec(select(STRING_PIECES.as_slice()), 0..5).prop_map(|pieces| pieces.concat())
     }
-    
-    // 3. Handle quoted strings: if it's "\"content\"", store "content"
-    if trimmed_s.starts_with('"') && trimmed_s.ends_with('"') && trimmed_s.len() >= 2 {
-        return Value::String(trimmed_s[1..trimmed_s.len()-1].to_string());
+
+    fn date_strategy() -> impl Strategy<Value = Date> {
+        (1900u16..2200, 1u8..=12)
+            .prop_flat_map(|(year, month)| (Just(year), Just(month), 1..=Date::days_in_month(year, month)))
+            .prop_map(|(year, month, day)| Date { year, month, day })
+    }
+
+    fn datetime_strategy() -> impl Strategy<Value = DateTime> {
+        (date_strategy(), 0u8..24, 0u8..60, 0u8..60).prop_map(|(date, hour, minute, second)| DateTime { date, hour, minute, second })
+    }
+
+    fn integer_strategy() -> BoxedStrategy<Value> {
+        (-100_000i64..100_000).prop_map(Value::Integer).boxed()
+    }
+
+    // Multiples of 1/64 print exactly, so the written text parses back to the same float
+    fn float_strategy() -> BoxedStrategy<Value> {
+        (-1_000_000i64..1_000_000).prop_map(|n| Value::Float(n as f64 / 64.0)).boxed()
     }
 
-    // 4. Default to string as is (if not quoted, not int, not bool)
-    Value::String(trimmed_s.to_string())
-}
\ No newline at end of file
+    // Cells of an untyped column, including tuples nested up to two levels (tuple items are
+    // parsed without a column type)
+    fn untyped_value_strategy() -> BoxedStrategy<Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            string_strategy().prop_map(Value::String),
+            integer_strategy(),
+            any::<bool>().prop_map(Value::Bool),
+            float_strategy(),
+        ];
+        leaf.prop_recursive(2, 16, 4, |inner| vec(inner, 0..4).prop_map(Value::Tuple)).boxed()
+    }
+
+    fn tuple_strategy() -> BoxedStrategy<Value> {
+        vec(untyped_value_strategy(), 0..4).prop_map(Value::Tuple).boxed()
+    }
+
+    fn value_strategy(type_info: Option<&str>) -> BoxedStrategy<Value> {
+        let null = || Just(Value::Null).boxed();
+        match type_info {
+            None => untyped_value_strategy(),
+            Some("boolean") => prop_oneof![null(), any::<bool>().prop_map(Value::Bool)].boxed(),
+            Some("float") => prop_oneof![null(), float_strategy()].boxed(),
+            Some("date") => prop_oneof![null(), date_strategy().prop_map(Value::Date)].boxed(),
+            Some("datetime") => prop_oneof![null(), datetime_strategy().prop_map(Value::DateTime)].boxed(),
+            Some("string") => prop_oneof![null(), string_strategy().prop_map(Value::String), tuple_strategy()].boxed(),
+            Some(_) => prop_oneof![null(), integer_strategy(), tuple_strategy()].boxed(),
+        }
+    }
+
+    fn table_strategy(name: String) -> impl Strategy<Value = Table> {
+        let key_kinds = vec![None, Some("sindex"), Some("index"), Some("gindex")];
+        let types = vec![None, Some("integer"), Some("string"), Some("boolean"), Some("float"), Some("date"), Some("datetime")];
+        (1..=4usize, select(key_kinds), any::<Index>(), vec(select(types), 4)).prop_flat_map(
+            move |(column_count, key_kind, key_column, types)| {
+                let key_column = key_column.index(column_count);
+                let headers: Vec<HeaderField> = (0..column_count)
+                    .map(|i| {
+                        let is_primary_key = key_kind.is_some() && i == key_column;
+                        let type_info = if is_primary_key { key_kind } else { types[i] };
+                        HeaderField { name: format!("c{}", i), type_info: type_info.map(str::to_string), is_primary_key }
+                    })
+                    .collect();
+                let cells: Vec<BoxedStrategy<Value>> = headers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, header)| match key_kind {
+                        Some("index") if i == key_column => string_strategy().prop_map(Value::String).boxed(),
+                        Some("gindex") if i == key_column => (0..2u8).prop_map(|g| Value::String(format!("g{}", g))).boxed(),
+                        _ => value_strategy(header.type_info.as_deref()),
+                    })
+                    .collect();
+                let name = name.clone();
+                vec(cells, 0..5).prop_map(move |rows| build_table(name.clone(), headers.clone(), key_kind, key_column, rows))
+            },
+        )
+    }
+
+    fn build_table(name: String, headers: Vec<HeaderField>, key_kind: Option<&str>, key_column: usize, rows: Vec<Vec<Value>>) -> Table {
+        let key_name = format!("c{}", key_column);
+        let rows: Vec<Row> = rows
+            .into_iter()
+            .enumerate()
+            .map(|(row_index, values)| {
+                let mut fields: HashMap<String, Value> =
+                    headers.iter().map(|header| header.name.clone()).zip(values).collect();
+                // `index` keys must be unique
+                if let (Some("index"), Some(Value::String(key))) = (key_kind, fields.get_mut(&key_name)) {
+                    key.push_str(&row_index.to_string());
+                }
+                Row { fields }
+            })
+            .collect();
+
+        let key_of = |row: &Row| value_to_string_key(&row.fields[&key_name]).unwrap();
+        let data = match key_kind {
+            Some("index") => TableData::Indexed(rows.into_iter().map(|row| (key_of(&row), row)).collect()),
+            Some("gindex") => {
+                let mut groups: HashMap<String, Vec<Row>> = HashMap::new();
+                for row in rows {
+                    groups.entry(key_of(&row)).or_default().push(row);
+                }
+                TableData::GroupedIndexed(groups)
+            }
+            _ => TableData::Sequential(rows),
+        };
+        Table {
+            header_map: headers.iter().enumerate().map(|(i, header)| (header.name.clone(), i)).collect(),
+            primary_key_field_name: key_kind.map(|_| key_name.clone()),
+            name,
+            headers,
+            data,
+        }
+    }
+
+    fn root_strategy() -> impl Strategy<Value = DslRoot> {
+        (1..=3usize)
+            .prop_flat_map(|count| (0..count).map(|i| table_strategy(format!("t{}", i))).collect::<Vec<_>>())
+            .prop_map(|tables| tables.into_iter().map(|table| (table.name.clone(), table)).collect())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(300))]
+
+        #[test]
+        fn test_packed_tables_parse_back_unchanged(root in root_strategy()) {
+            let mut names: Vec<String> = root.keys().cloned().collect();
+            names.sort();
+            let packed = execute_pack(&root, &names).unwrap();
+            let statements = parse_dsl_input(&packed, None)
+                .map_err(|errors| TestCaseError::fail(format!("{}\n{}", errors.render(), packed)))?;
+            let mut parsed = DslRoot::new();
+            for statement in statements {
+                match statement {
+                    DslStatement::Definition(name, table) => {
+                        parsed.insert(name, table);
+                    }
+                    other => return Err(TestCaseError::fail(format!("unexpected statement {:?}", other))),
+                }
+            }
+            prop_assert_eq!(parsed, root, "packed:\n{}", packed);
+        }
+    }
+}
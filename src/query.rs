@@ -1,11 +1,10 @@
 use std::collections::HashMap;
-use crate::structs::{Value, Row, DslRoot, TableData, Table};
-use crate::parser::{value_to_string_key, parse_value_str};
-use crate::structs::HeaderField;
+use crate::structs::{Value, Row, DslRoot, TableData};
+use crate::parser::{value_to_string_key, try_parse_value_str, string_literal};
 
 fn is_primitive_or_special_type(type_name: &str) -> bool {
     matches!(type_name.to_lowercase().as_str(),
-        "integer" | "string" | "boolean" | "date" | "datetime" | // Known primitive types
+        "integer" | "string" | "boolean" | "float" | "date" | "datetime" | // Known primitive types
         "sindex" | "index" | "gindex" | "config" | "system" | // Known special directive types
         _ if type_name.contains("::") // Covers references like "any::table" or specific type hints
     )
@@ -183,7 +182,7 @@ pub fn execute_query<'a>(root: &'a DslRoot, query_path_str: &str) -> Option<&'a
                 if let Some(val) = field_val_opt {
                     // Determine if this value (now current_value_context) is a tuple with a known structure
                     if let Value::Tuple(_) = val {
-                        let original_table_name_for_header = current_table_name.as_ref().ok_or_else(|| { /* Should not happen */ None::<&Value> }).unwrap();
+                        let original_table_name_for_header = current_table_name.as_ref()?;
                         if let Some(original_table_def) = root.get(original_table_name_for_header) {
                             if let Some(header_field) = original_table_def.headers.iter().find(|h| h.name == part_str) {
                                 if let Some(type_info) = &header_field.type_info {
@@ -199,10 +198,7 @@ pub fn execute_query<'a>(root: &'a DslRoot, query_path_str: &str) -> Option<&'a
                     if let Value::Reference { type_name: ref_table_str, key: ref_key_val_boxed } = val {
                         let ref_key_val = &**ref_key_val_boxed;
                         current_table_name = Some(ref_table_str.clone()); // Update current_table_name for next part
-                        let referenced_table = match root.get(ref_table_str) {
-                            Some(rt) => rt,
-                            None => return None,
-                        };
+                        let referenced_table = root.get(ref_table_str)?;
 
                         match &referenced_table.data {
                             TableData::Sequential(rows) => {
@@ -227,7 +223,7 @@ pub fn execute_query<'a>(root: &'a DslRoot, query_path_str: &str) -> Option<&'a
                         }
                         current_value_context = None; // After dereferencing, context is now a row or None
                         current_tuple_structure_name = None; // Reset as we are now in a new row context
-                        if current_row_context.is_none() { return None; }
+                        current_row_context?;
                     } else {
                         // It's not a reference, so we are now focused on 'val'.
                         // The row context is no longer the primary focus for the next part of the path.
@@ -251,13 +247,11 @@ pub fn execute_query<'a>(root: &'a DslRoot, query_path_str: &str) -> Option<&'a
                                     current_gindexed_rows_context = None;
 
                                     // If the new current_value_context is also a tuple, determine its structure name
-                                    if let Some(new_val) = current_value_context {
-                                        if let Value::Tuple(_) = new_val {
-                                            if let Some(sub_header) = structure_table_def.headers.get(*field_idx_in_tuple) {
-                                                if let Some(sub_type_info) = &sub_header.type_info {
-                                                    if root.contains_key(sub_type_info) && !is_primitive_or_special_type(sub_type_info) {
-                                                        current_tuple_structure_name = Some(sub_type_info.clone());
-                                                    }
+                                    if let Some(Value::Tuple(_)) = current_value_context {
+                                        if let Some(sub_header) = structure_table_def.headers.get(*field_idx_in_tuple) {
+                                            if let Some(sub_type_info) = &sub_header.type_info {
+                                                if root.contains_key(sub_type_info) && !is_primitive_or_special_type(sub_type_info) {
+                                                    current_tuple_structure_name = Some(sub_type_info.clone());
                                                 }
                                             }
                                         }
@@ -271,8 +265,8 @@ pub fn execute_query<'a>(root: &'a DslRoot, query_path_str: &str) -> Option<&'a
             } else { // No current_row_context and no current_value_context (e.g. #.table.field)
                 match &table_to_query.data {
                     TableData::Sequential(rows) => {
-                        if rows.len() >= 1 {
-                            current_row_context = rows.get(0); 
+                        if !rows.is_empty() {
+                            current_row_context = rows.first(); 
                             if current_row_context.is_some() {
                                 field_val_opt = current_row_context.unwrap().fields.get(part_str); 
                                 if let Some(Value::Reference{type_name: ref_table_str, key: ref_key_val_boxed}) = field_val_opt {
@@ -308,7 +302,7 @@ pub fn execute_query<'a>(root: &'a DslRoot, query_path_str: &str) -> Option<&'a
                                         TableData::RawLines(_) => return None, 
                                     }
                                     current_value_context = None;
-                                    if current_row_context.is_none() { return None; }
+                                    current_row_context?;
                                 } else {
                                     current_value_context = field_val_opt;
                                 }
@@ -350,9 +344,8 @@ fn find_value_mut<'a>(
 
     let mut _current_table_name: Option<String> = None; 
     let mut _current_table_primary_key_type: Option<String> = None; 
-    let mut final_field_name_for_type_lookup: Option<String> = None;
 
-    if parts.len() < 1 { 
+    if parts.is_empty() { 
         return Err("Query path is too short.".to_string());
     }
     
@@ -429,7 +422,7 @@ fn find_value_mut<'a>(
     }
 
     let field_name_str = &parts[part_idx];
-    final_field_name_for_type_lookup = Some(field_name_str.clone());
+    let final_field_name_for_type_lookup = Some(field_name_str.clone());
     
     let field_to_update = target_row.get_field_mut(field_name_str)
         .ok_or_else(|| format!("Field '{}' not found in table '{}'", field_name_str, table.name))?;
@@ -465,7 +458,7 @@ fn find_value_mut<'a>(
     }
     
     let field_type_info: Option<String> = table.headers.iter()
-        .find(|h| final_field_name_for_type_lookup.as_ref().map_or(false, |name| &h.name == name))
+        .find(|h| final_field_name_for_type_lookup.as_ref() == Some(&h.name))
         .and_then(|h| h.type_info.clone());
 
     Ok((final_target_value, field_type_info))
@@ -479,7 +472,21 @@ pub fn execute_update(
 ) -> Result<(), String> {
     let (target_value, field_type_info) = find_value_mut(root, path_str)?; 
     
-    let new_value = parse_value_str(value_str, field_type_info.as_deref());
+    let new_value = try_parse_value_str(value_str, field_type_info.as_deref())
+        .map_err(|e| format!("Type mismatch for path '{}': {}", path_str, e))?;
+
+    // boolean, float, date and datetime columns only hold their own type (or Null)
+    if let Some(expected) = field_type_info.as_deref().and_then(strict_value_type) {
+        let received = value_type_to_string(&new_value);
+        if received != expected && new_value != Value::Null {
+            return Err(format!(
+                "Type mismatch for path '{}': column holds {}, but received value is {} ('{}')",
+                path_str, expected, received, value_str
+            ));
+        }
+        *target_value = new_value;
+        return Ok(());
+    }
 
     match (target_value.clone(), &new_value) { 
         (Value::Integer(_), Value::String(s)) => {
@@ -524,10 +531,25 @@ pub fn execute_update(
     Ok(())
 }
 
-fn value_type_to_string(value: &Value) -> &'static str {
+// The value type of a column type that is checked strictly
+fn strict_value_type(type_info: &str) -> Option<&'static str> {
+    match type_info.to_lowercase().as_str() {
+        "boolean" => Some("Bool"),
+        "float" => Some("Float"),
+        "date" => Some("Date"),
+        "datetime" => Some("DateTime"),
+        _ => None,
+    }
+}
+
+pub(crate) fn value_type_to_string(value: &Value) -> &'static str {
     match value {
         Value::String(_) => "String",
         Value::Integer(_) => "Integer",
+        Value::Bool(_) => "Bool",
+        Value::Float(_) => "Float",
+        Value::Date(_) => "Date",
+        Value::DateTime(_) => "DateTime",
         Value::Tuple(_) => "Tuple",
         Value::Reference { .. } => "Reference",
         Value::Null => "Null",
@@ -549,28 +571,26 @@ pub fn execute_add(root: &mut DslRoot, table_name_str: &str) -> Result<(), Strin
 
 // --- Pack Operation ---
 
-fn serialize_value(value: &Value, header_map: &HashMap<String, usize>, headers: &[HeaderField]) -> String {
+fn serialize_value(value: &Value) -> String {
     match value {
-        Value::String(s) => {
-            if s.contains(',') || s.contains('(') || s.contains(')') || s.contains('\'') || s.contains(' ') || s.is_empty() {
-                format!("'{}'", s.replace('\'', "''")) 
-            } else {
-                s.clone()
-            }
-        }
+        Value::String(s) => string_literal(s),
         Value::Integer(i) => i.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Float(f) => format!("{:?}", f), // Debug keeps the ".0" so "3.0" reads back as a float
+        Value::Date(date) => date.to_string(),
+        Value::DateTime(datetime) => datetime.to_string(),
         Value::Tuple(items) => {
             let item_strs: Vec<String> = items
                 .iter()
-                .map(|item| serialize_value(item, header_map, headers)) 
+                .map(serialize_value)
                 .collect();
             format!("({})", item_strs.join(","))
         }
         Value::Reference { type_name: _type_name, key } => { 
-            let key_str = serialize_value(key, header_map, headers); 
+            let key_str = serialize_value(key);
             format!("({})", key_str) 
         }
-        Value::Null => "".to_string(),  
+        Value::Null => "null".to_string(), // An empty cell would drop a single-column row
     }
 }
 
@@ -587,15 +607,10 @@ pub fn execute_pack(root: &DslRoot, table_names: &[String]) -> Result<String, St
 
         if !table.headers.is_empty() {
             let header_parts: Vec<String> = table.headers.iter().map(|h| {
-                let mut part = h.name.clone();
-                if let Some(type_info) = &h.type_info {
-                    if h.is_primary_key && (type_info == "index" || type_info == "gindex" || type_info == "sindex") {
-                        part = format!("{}:{}", h.name, type_info);
-                    } else {
-                        part = format!("{}::{}", h.name, type_info);
-                    }
+                match &h.type_info {
+                    Some(type_info) => format!("{}::{}", h.name, type_info),
+                    None => h.name.clone(),
                 }
-                part
             }).collect();
             table_content.push_str(&format!("/{}/\n", header_parts.join("/")));
         } else {
@@ -607,7 +622,7 @@ pub fn execute_pack(root: &DslRoot, table_names: &[String]) -> Result<String, St
                 for row in rows {
                     let row_values: Vec<String> = table.headers.iter().map(|header_field| {
                         row.fields.get(&header_field.name)
-                            .map_or("".to_string(), |value| serialize_value(value, &table.header_map, &table.headers))
+                            .map_or("".to_string(), serialize_value)
                     }).collect();
                     table_content.push_str(&format!("{}\n", row_values.join(",")));
                 }
@@ -623,7 +638,7 @@ pub fn execute_pack(root: &DslRoot, table_names: &[String]) -> Result<String, St
                     let row = &map[key];
                     let row_values: Vec<String> = table.headers.iter().map(|header_field| {
                         row.fields.get(&header_field.name)
-                            .map_or("".to_string(), |value| serialize_value(value, &table.header_map, &table.headers))
+                            .map_or("".to_string(), serialize_value)
                     }).collect();
                     table_content.push_str(&format!("{}\n", row_values.join(",")));
                 }
@@ -637,7 +652,7 @@ pub fn execute_pack(root: &DslRoot, table_names: &[String]) -> Result<String, St
                     for row in rows_in_group {
                         let row_values: Vec<String> = table.headers.iter().map(|header_field| {
                             row.fields.get(&header_field.name)
-                                .map_or("".to_string(), |value| serialize_value(value, &table.header_map, &table.headers))
+                                .map_or("".to_string(), serialize_value)
                         }).collect();
                         table_content.push_str(&format!("{}\n", row_values.join(",")));
                     }
@@ -648,4 +663,37 @@ pub fn execute_pack(root: &DslRoot, table_names: &[String]) -> Result<String, St
     }
 
     Ok(packed_strings.join("\n~\n")) 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_dsl_input, DslStatement};
+    use crate::structs::Date;
+
+    fn root(input: &str) -> DslRoot {
+        parse_dsl_input(input, None)
+            .unwrap()
+            .into_iter()
+            .filter_map(|statement| match statement {
+                DslStatement::Definition(name, table) => Some((name, table)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_update_checks_typed_columns() {
+        let mut root = root("item:\n/id::sindex/price::float/due::date/done::boolean/\n0,1.5,2025-01-31,false\n~");
+        execute_update(&mut root, "item[0].price", "2").unwrap();
+        execute_update(&mut root, "item[0].due", "2025-02-28").unwrap();
+        execute_update(&mut root, "item[0].done", "null").unwrap();
+        assert_eq!(execute_query(&root, "#.item[0].price"), Some(&Value::Float(2.0)));
+        assert_eq!(execute_query(&root, "#.item[0].due"), Some(&Value::Date(Date { year: 2025, month: 2, day: 28 })));
+        assert_eq!(execute_query(&root, "#.item[0].done"), Some(&Value::Null));
+
+        assert!(execute_update(&mut root, "item[0].due", "2025-02-30").unwrap_err().contains("day is out of range"));
+        assert!(execute_update(&mut root, "item[0].price", "'2'").unwrap_err().contains("column holds Float"));
+        assert!(execute_update(&mut root, "item[0].done", "yes").is_err());
+        assert_eq!(execute_query(&root, "#.item[0].price"), Some(&Value::Float(2.0)));
+    }
+}
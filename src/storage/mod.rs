@@ -1,41 +1,461 @@
 // src/storage/mod.rs
-use std::collections::HashMap;
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(Clone)] // 添加 Clone trait 以支持 snapshot
+// 数据目录中的文件名
+const DATA_FILE_NAME: &str = "data.json";
+const DATA_TEMP_FILE_NAME: &str = "data.json.tmp";
+
+// 一个键在某个提交时间戳上的版本；value 为 None 表示该版本是删除标记 (tombstone)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Version {
+    pub value: Option<String>,
+    pub commit_ts: u64,
+    #[serde(default)]
+    pub committed_at: u64, // 提交时的墙上时间 (Unix 毫秒)，用于按时间保留历史版本；从日志重放时恢复提交时记录的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>, // 过期时间 (Unix 毫秒)，过期后该版本对所有读取都不可见
+}
+
+impl Version {
+    // 版本在 now (Unix 毫秒) 时是否有可见的值 (删除标记和已过期的版本没有)
+    fn live_value(&self, now: u64) -> Option<&String> {
+        match self.expires_at {
+            Some(expires_at) if expires_at <= now => None,
+            _ => self.value.as_ref(),
+        }
+    }
+}
+
+// 根据 TTL 计算过期时间 (Unix 毫秒)
+pub fn expires_at_after(ttl: Duration) -> u64 {
+    unix_millis().saturating_add(ttl.as_millis() as u64)
+}
+
+// 历史版本的保留策略：满足任一条件的版本都会被保留，
+// 另外被活动事务或命名快照引用的版本永远不会被回收，每个键的最新版本也总是保留
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetentionPolicy {
+    pub keep_last: usize, // 每个键保留最近的 N 个版本 (包括最新版本)
+    pub keep_newer_than: Option<Duration>, // 保留提交时间在此时长之内的版本
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_last: 10,
+            keep_newer_than: None,
+        }
+    }
+}
+
+// 一次垃圾回收的结果
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct GcStats {
+    pub versions_removed: usize,
+    pub keys_removed: usize, // 只剩删除标记的键被整体移除
+    pub bytes_reclaimed: usize, // 被回收的键和值的字节数 (近似)
+}
+
+// 扫描的键范围：[start, end) 内并且以 prefix 开头的键，按键的字典序返回
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyRange {
+    pub start: Bound<String>,
+    pub end: Bound<String>,
+    pub prefix: String,
+}
+
+impl Default for KeyRange {
+    fn default() -> Self {
+        KeyRange {
+            start: Bound::Unbounded,
+            end: Bound::Unbounded,
+            prefix: String::new(),
+        }
+    }
+}
+
+impl KeyRange {
+    pub fn prefix(prefix: &str) -> Self {
+        KeyRange {
+            prefix: prefix.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // 起点包含在内、终点不包含在内的范围，end 为 None 表示扫描到最后
+    pub fn between(start: &str, end: Option<&str>) -> Self {
+        KeyRange {
+            start: Bound::Included(start.to_string()),
+            end: end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_string())),
+            prefix: String::new(),
+        }
+    }
+
+    // 扫描的起点：start 与 prefix 中较大的一个
+    pub fn lower_bound(&self) -> Bound<&str> {
+        let start = match &self.start {
+            Bound::Included(start) => Bound::Included(start.as_str()),
+            Bound::Excluded(start) => Bound::Excluded(start.as_str()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        match start {
+            Bound::Included(key) | Bound::Excluded(key) if key >= self.prefix.as_str() => start,
+            _ => Bound::Included(self.prefix.as_str()),
+        }
+    }
+
+    // 只包含一个键的范围
+    pub fn single(key: &str) -> Self {
+        KeyRange {
+            start: Bound::Included(key.to_string()),
+            end: Bound::Included(key.to_string()),
+            prefix: String::new(),
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        let after_start = match self.lower_bound() {
+            Bound::Included(start) => key >= start,
+            Bound::Excluded(start) => key > start,
+            Bound::Unbounded => true,
+        };
+        after_start && !self.is_past_end(key)
+    }
+
+    // 按顺序扫描时，遇到第一个越过范围的键即可停止
+    pub fn is_past_end(&self, key: &str) -> bool {
+        let past_end = match &self.end {
+            Bound::Included(end) => key > end.as_str(),
+            Bound::Excluded(end) => key >= end.as_str(),
+            Bound::Unbounded => false,
+        };
+        past_end || (key > self.prefix.as_str() && !key.starts_with(&self.prefix))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)] // 添加 Clone trait 以支持 snapshot，Serialize/Deserialize 用于持久化
 pub struct StorageEngine {
-    data: HashMap<String, String>,
-    versions: HashMap<String, Vec<String>>, // 用于存储每个键的历史版本
+    data: BTreeMap<String, Version>, // 每个键最新提交的版本，按键排序以支持范围扫描
+    versions: HashMap<String, Vec<Version>>, // 用于存储每个键被覆盖的历史版本 (从旧到新)
+    #[serde(default)]
+    last_commit_ts: u64, // 最后一次提交使用的时间戳，每次提交递增
+    #[serde(default)]
+    named_snapshots: BTreeMap<String, u64>, // 命名快照 -> 创建时的提交时间戳 (只保存指针，不复制数据)
+    #[serde(default)]
+    committed_transactions: BTreeMap<u64, u64>, // 已提交的事务 ID -> 提交时间戳，用于按事务 ID 做时间点读取
+    #[serde(default)]
+    oldest_readable_ts: u64, // 垃圾回收后，早于此时间戳的时间点读取可能缺少版本
+    #[serde(default)]
+    last_transaction_id: u64, // 最后分配的事务 ID，随数据一起持久化以保证 ID 不被复用
+    #[serde(skip)]
+    data_dir: Option<PathBuf>, // 持久化目录，None 表示纯内存引擎
+}
+
+impl Default for StorageEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StorageEngine {
     pub fn new() -> Self {
         println!("StorageEngine initialized.");
         StorageEngine {
-            data: HashMap::new(),
+            data: BTreeMap::new(),
             versions: HashMap::new(),
+            last_commit_ts: 0,
+            named_snapshots: BTreeMap::new(),
+            committed_transactions: BTreeMap::new(),
+            oldest_readable_ts: 0,
+            last_transaction_id: 0,
+            data_dir: None,
+        }
+    }
+
+    // 打开一个以目录为根的持久化引擎：目录不存在时创建，已有数据文件时加载
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        println!("StorageEngine: Opening data directory '{}'", dir.display());
+        fs::create_dir_all(dir)?;
+        if dir.join(DATA_FILE_NAME).exists() {
+            Self::load_from_disk(dir)
+        } else {
+            let mut engine = Self::new();
+            engine.data_dir = Some(dir.to_path_buf());
+            Ok(engine)
+        }
+    }
+
+    pub fn data_dir(&self) -> Option<&Path> {
+        self.data_dir.as_deref()
+    }
+
+    pub fn last_transaction_id(&self) -> u64 {
+        self.last_transaction_id
+    }
+
+    // 记录已经使用过的事务 ID (分配新事务或从日志恢复时调用)
+    pub fn observe_transaction_id(&mut self, transaction_id: u64) {
+        self.last_transaction_id = self.last_transaction_id.max(transaction_id);
+    }
+
+    // 记录事务的提交时间戳 (提交或从日志重放时调用)
+    pub fn record_transaction_commit(&mut self, transaction_id: u64, commit_ts: u64) {
+        self.committed_transactions.insert(transaction_id, commit_ts);
+    }
+
+    // 事务的提交时间戳；未提交 (或已回滚) 的事务返回 None
+    pub fn transaction_commit_ts(&self, transaction_id: u64) -> Option<u64> {
+        self.committed_transactions.get(&transaction_id).copied()
+    }
+
+    // 最近一次提交的时间戳；新事务以它作为自己的快照
+    pub fn last_commit_ts(&self) -> u64 {
+        self.last_commit_ts
+    }
+
+    // 以一个新的提交时间戳原子地应用一组写入 (None 表示删除)，返回该时间戳
+    pub fn commit_writes<I>(&mut self, writes: I) -> u64
+    where
+        I: IntoIterator<Item = (String, Option<String>)>,
+    {
+        let commit_ts = self.last_commit_ts + 1;
+        self.commit_writes_at(writes, commit_ts);
+        commit_ts
+    }
+
+    // 以指定的提交时间戳应用一组写入，用于从日志重放 (时间戳必须大于 last_commit_ts)
+    pub fn commit_writes_at<I>(&mut self, writes: I, commit_ts: u64)
+    where
+        I: IntoIterator<Item = (String, Option<String>)>,
+    {
+        self.commit_expiring_writes_at(writes.into_iter().map(|(key, value)| (key, value, None)), commit_ts);
+    }
+
+    // 与 commit_writes 相同，但每个写入可以带一个过期时间 (Unix 毫秒)
+    pub fn commit_expiring_writes<I>(&mut self, writes: I) -> u64
+    where
+        I: IntoIterator<Item = (String, Option<String>, Option<u64>)>,
+    {
+        let commit_ts = self.last_commit_ts + 1;
+        self.commit_expiring_writes_at(writes, commit_ts);
+        commit_ts
+    }
+
+    pub fn commit_expiring_writes_at<I>(&mut self, writes: I, commit_ts: u64)
+    where
+        I: IntoIterator<Item = (String, Option<String>, Option<u64>)>,
+    {
+        self.commit_expiring_writes_at_time(writes, commit_ts, unix_millis());
+    }
+
+    // 同时指定提交时的墙上时间 (Unix 毫秒)：提交时与日志中记录的时间一致，重放时恢复原来的时间
+    pub fn commit_expiring_writes_at_time<I>(&mut self, writes: I, commit_ts: u64, committed_at: u64)
+    where
+        I: IntoIterator<Item = (String, Option<String>, Option<u64>)>,
+    {
+        assert!(commit_ts > self.last_commit_ts, "commit timestamps must increase");
+        self.last_commit_ts = commit_ts;
+        for (key, value, expires_at) in writes {
+            self.put_version(key, Version { value, commit_ts, committed_at, expires_at });
+        }
+    }
+
+    // 将新版本设为最新版本，原来的最新版本进入历史
+    fn put_version(&mut self, key: String, version: Version) {
+        if let Some(old_version) = self.data.insert(key.clone(), version) {
+            self.versions.entry(key).or_default().push(old_version);
         }
     }
 
     pub fn write(&mut self, key: String, value: String) {
         println!("StorageEngine: Writing data for key: '{}', value: '{}'", key, value);
         // 在写入新值时，将旧值（如果存在）保存到历史版本中
-        if let Some(old_value) = self.data.get(&key) {
-            self.versions.entry(key.clone()).or_insert_with(Vec::new).push(old_value.clone());
-        }
-        self.data.insert(key, value);
+        self.commit_writes([(key, Some(value))]);
+    }
+
+    // 写入一个在 ttl 之后过期的值；ttl 为 None 时与 write 相同
+    pub fn write_with_ttl(&mut self, key: String, value: String, ttl: Option<Duration>) {
+        println!("StorageEngine: Writing data for key: '{}', value: '{}' with TTL {:?}", key, value, ttl);
+        self.commit_expiring_writes([(key, Some(value), ttl.map(expires_at_after))]);
+    }
+
+    // 最新版本已经过期 (但还没有被删除标记覆盖) 的键，由后台任务定期清除
+    pub fn expired_keys(&self) -> Vec<String> {
+        let now = unix_millis();
+        self.data
+            .iter()
+            .filter(|(_, latest)| latest.value.is_some() && latest.live_value(now).is_none())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    // 在时间戳 ts (含) 时可见的版本是否是一个已经过期的值 (删除标记和未过期的值都不是)
+    pub fn is_expired_at(&self, key: &String, ts: u64) -> bool {
+        let Some(latest) = self.data.get(key) else {
+            return false;
+        };
+        let version = if latest.commit_ts <= ts {
+            Some(latest)
+        } else {
+            self.versions.get(key).and_then(|history| history.iter().rev().find(|version| version.commit_ts <= ts))
+        };
+        version.is_some_and(|version| version.value.is_some() && version.live_value(unix_millis()).is_none())
     }
 
     pub fn read(&self, key: &String) -> Option<String> {
-        println!("StorageEngine: Reading data for key: '{}'", key);
-        self.data.get(key).cloned()
+        self.data.get(key).and_then(|version| version.live_value(unix_millis()).cloned())
+    }
+
+    // 读取在时间戳 ts (含) 时已提交的值，用于快照隔离
+    pub fn read_at(&self, key: &String, ts: u64) -> Option<String> {
+        self.data.get(key).and_then(|latest| self.value_at(key, latest, ts))
+    }
+
+    // 已过期的版本在任何时间戳上都不可见
+    fn value_at(&self, key: &String, latest: &Version, ts: u64) -> Option<String> {
+        let now = unix_millis();
+        if latest.commit_ts <= ts {
+            return latest.live_value(now).cloned();
+        }
+        self.versions
+            .get(key)
+            .and_then(|history| history.iter().rev().find(|version| version.commit_ts <= ts))
+            .and_then(|version| version.live_value(now).cloned())
+    }
+
+    // 按键的顺序遍历范围内在时间戳 ts (含) 时可见的键值对，删除标记和之后才写入的键会被跳过
+    pub fn scan_at<'a>(&'a self, range: &'a KeyRange, ts: u64) -> impl Iterator<Item = (String, String)> + 'a {
+        self.data
+            .range::<str, _>((range.lower_bound(), Bound::Unbounded))
+            .take_while(move |(key, _)| !range.is_past_end(key))
+            .filter_map(move |(key, latest)| self.value_at(key, latest, ts).map(|value| (key.clone(), value)))
+    }
+
+    // 范围内提交时间戳大于 since_ts 的所有版本 (包括删除标记)，按 (提交时间戳, 键) 排序，用于重放变更
+    pub fn versions_since(&self, range: &KeyRange, since_ts: u64) -> Vec<(String, Version)> {
+        let mut changes: Vec<(String, Version)> = self
+            .data
+            .range::<str, _>((range.lower_bound(), Bound::Unbounded))
+            .take_while(|(key, _)| !range.is_past_end(key))
+            .flat_map(|(key, _)| {
+                self.history(key)
+                    .into_iter()
+                    .filter(|version| version.commit_ts > since_ts)
+                    .map(move |version| (key.clone(), version))
+            })
+            .collect();
+        changes.sort_by(|(a_key, a), (b_key, b)| a.commit_ts.cmp(&b.commit_ts).then_with(|| a_key.cmp(b_key)));
+        changes
+    }
+
+    // 范围内第一个在 since_ts 之后被提交过 (写入或删除) 的键
+    pub fn first_modified_since(&self, range: &KeyRange, since_ts: u64) -> Option<String> {
+        self.data
+            .range::<str, _>((range.lower_bound(), Bound::Unbounded))
+            .take_while(|(key, _)| !range.is_past_end(key))
+            .find(|(_, latest)| latest.commit_ts > since_ts)
+            .map(|(key, _)| key.clone())
+    }
+
+    // 扫描 [start, end) 内最新的键值对，最多返回 limit 个
+    pub fn scan(&self, start: &str, end: Option<&str>, limit: usize) -> Vec<(String, String)> {
+        let range = KeyRange::between(start, end);
+        self.scan_at(&range, self.last_commit_ts).take(limit).collect()
+    }
+
+    // 扫描以 prefix 开头的全部最新键值对
+    pub fn scan_prefix(&self, prefix: &str) -> Vec<(String, String)> {
+        let range = KeyRange::prefix(prefix);
+        self.scan_at(&range, self.last_commit_ts).collect()
+    }
+
+    // 键最新版本的提交时间戳 (包括删除标记)
+    pub fn latest_commit_ts(&self, key: &String) -> Option<u64> {
+        self.data.get(key).map(|version| version.commit_ts)
+    }
+
+    // 键的全部版本 (包括删除标记)，按提交时间戳从旧到新排列，最后一个是最新版本
+    pub fn history(&self, key: &String) -> Vec<Version> {
+        let mut history = self.versions.get(key).cloned().unwrap_or_default();
+        history.extend(self.data.get(key).cloned());
+        history
+    }
+
+    pub fn oldest_readable_ts(&self) -> u64 {
+        self.oldest_readable_ts
+    }
+
+    // 按保留策略回收历史版本。pinned 是必须仍能读到的时间戳 (活动事务的快照)，命名快照会自动加入
+    // 一个被覆盖的版本在 [自己的 commit_ts, 下一个版本的 commit_ts) 内可见，区间内有被引用的时间戳时保留
+    pub fn collect_garbage(&mut self, policy: &RetentionPolicy, pinned: &[u64]) -> GcStats {
+        let mut pins: Vec<u64> = pinned.to_vec();
+        pins.extend(self.named_snapshots.values().copied());
+        let is_pinned = |from: u64, until: u64| pins.iter().any(|&ts| from <= ts && ts < until);
+        let now = unix_millis();
+        let is_recent = |version: &Version| match policy.keep_newer_than {
+            Some(age) => now.saturating_sub(version.committed_at) < age.as_millis() as u64,
+            None => false,
+        };
+
+        let mut stats = GcStats::default();
+        let mut oldest_readable_ts = self.oldest_readable_ts;
+        for (key, history) in self.versions.iter_mut() {
+            let latest_ts = self.data[key].commit_ts;
+            let total = history.len() + 1; // 包括最新版本
+            let next_ts: Vec<u64> = history.iter().skip(1).map(|version| version.commit_ts).chain([latest_ts]).collect();
+            let mut kept = Vec::with_capacity(history.len());
+            for (index, version) in history.drain(..).enumerate() {
+                let until = next_ts[index];
+                if total - index <= policy.keep_last || is_recent(&version) || is_pinned(version.commit_ts, until) {
+                    kept.push(version);
+                } else {
+                    stats.versions_removed += 1;
+                    stats.bytes_reclaimed += key.len() + version.value.as_ref().map_or(0, String::len);
+                    oldest_readable_ts = oldest_readable_ts.max(until);
+                }
+            }
+            *history = kept;
+        }
+        self.versions.retain(|_, history| !history.is_empty());
+
+        // 只剩删除标记的键：没有早于删除的快照需要它 (冲突检测也不再需要) 时整体移除
+        let removable: Vec<String> = self
+            .data
+            .iter()
+            .filter(|(key, latest)| {
+                latest.value.is_none()
+                    && !self.versions.contains_key(*key)
+                    && !is_recent(latest)
+                    && !pins.iter().any(|&ts| ts < latest.commit_ts)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in removable {
+            self.data.remove(&key);
+            stats.keys_removed += 1;
+            stats.bytes_reclaimed += key.len();
+        }
+
+        // 早于可读时间戳的事务已无法做时间点读取，不再需要记录它们的提交时间戳
+        self.committed_transactions.retain(|_, commit_ts| *commit_ts >= oldest_readable_ts);
+        self.oldest_readable_ts = oldest_readable_ts;
+        stats
     }
 
     // 获取指定键的特定历史版本
     pub fn get_version(&self, key: &String, version_index: usize) -> Option<String> {
         println!("StorageEngine: Getting version {} for key: '{}'", version_index, key);
-        // 确保从 versions.get(key) 返回的 Vec<String> 中正确索引并克隆值
+        // 删除标记没有值，返回 None
         self.versions.get(key).and_then(|versions| {
-            versions.get(version_index).cloned()
+            versions.get(version_index).and_then(|version| version.value.clone())
         })
     }
 
@@ -52,42 +472,128 @@ impl StorageEngine {
         self.versions = snapshot.versions;
     }
 
-    // 实现 delete 方法
+    // 记录一个指向提交时间戳 ts 的命名快照；已存在同名快照时返回 false
+    pub fn add_named_snapshot(&mut self, snapshot_id: String, ts: u64) -> bool {
+        if self.named_snapshots.contains_key(&snapshot_id) {
+            return false;
+        }
+        self.named_snapshots.insert(snapshot_id, ts);
+        true
+    }
+
+    pub fn named_snapshot(&self, snapshot_id: &str) -> Option<u64> {
+        self.named_snapshots.get(snapshot_id).copied()
+    }
+
+    pub fn remove_named_snapshot(&mut self, snapshot_id: &str) -> Option<u64> {
+        self.named_snapshots.remove(snapshot_id)
+    }
+
+    // 所有命名快照，按名称排序
+    pub fn named_snapshots(&self) -> &BTreeMap<String, u64> {
+        &self.named_snapshots
+    }
+
+    // 计算把时间戳 current_ts 时的状态恢复成时间戳 ts 时的状态所需的写入 (None 表示删除)
+    pub fn writes_to_restore(&self, ts: u64, current_ts: u64) -> Vec<(String, Option<String>)> {
+        let mut writes: Vec<(String, Option<String>)> = self
+            .data
+            .iter()
+            .filter_map(|(key, latest)| {
+                let old_value = self.value_at(key, latest, ts);
+                (old_value != self.value_at(key, latest, current_ts)).then(|| (key.clone(), old_value))
+            })
+            .collect();
+        writes.sort();
+        writes
+    }
+
+    // 条件写入 (直接作用于最新提交的数据)：当前值等于 expected (None 表示不存在) 时写入 new_value (None 表示删除)，
+    // 否则返回 Err(当前值)
+    pub fn compare_and_swap(&mut self, key: &String, expected: Option<&str>, new_value: Option<String>) -> Result<(), Option<String>> {
+        println!("StorageEngine: Compare-and-swap on key: '{}'", key);
+        let actual = self.read(key);
+        if actual.as_deref() != expected {
+            return Err(actual);
+        }
+        if actual.is_some() || new_value.is_some() {
+            self.commit_writes([(key.clone(), new_value)]);
+        }
+        Ok(())
+    }
+
+    // 删除键：写入一个删除标记版本，使快照读取仍能看到删除前的值
     pub fn delete(&mut self, key: &String) -> Option<String> {
         println!("StorageEngine: Deleting data for key: '{}'", key);
-        // 为了简单起见，暂时只从 data 中删除
-        // 未来可以考虑如何处理其在 versions 中的历史
-        // 例如，可以保留历史版本或添加一个特殊的“已删除”标记。
-        // 如果需要将删除也视为一个版本，可以在删除前将当前值存入 versions
-        // if let Some(old_value) = self.data.get(key) {
-        //     self.versions.entry(key.clone()).or_insert_with(Vec::new).push(old_value.clone());
-        // }
-        self.data.remove(key)
+        let old_value = self.read(key);
+        if old_value.is_some() {
+            self.commit_writes([(key.clone(), None)]);
+        }
+        old_value
     }
 
-    // 用于事务回滚时，如果键是新创建的，则需要删除
-    // 这个方法与上面的 delete 类似，但接受 String 而不是 &String，并且不特意处理版本历史
-    // TxnMgr 将使用此方法
-    pub fn delete_data(&mut self, key: String) -> Option<String> {
-        println!("StorageEngine: (Internal) Deleting data for key: '{}'", key);
-        self.data.remove(&key)
+    // 从数据目录加载 data 与 versions；数据文件不存在时返回 NotFound
+    pub fn load_from_disk<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let path = dir.join(DATA_FILE_NAME);
+        println!("StorageEngine: Loading data from '{}'", path.display());
+        let content = fs::read(&path)?;
+        let mut engine: StorageEngine = serde_json::from_slice(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Corrupted data file '{}': {}", path.display(), e)))?;
+        engine.data_dir = Some(dir.to_path_buf());
+        Ok(engine)
     }
 
-    pub fn load_from_disk() {
-        // TODO: 实现从磁盘加载数据逻辑
-        println!("StorageEngine: Loading data from disk (Not yet implemented)");
-    }
+    // 原子地将内存中的数据刷新到磁盘：先写临时文件并 fsync，再 rename 覆盖正式文件
+    // 纯内存引擎 (没有 data_dir) 调用此方法不做任何事
+    pub fn flush_to_disk(&self) -> Result<(), Error> {
+        let dir = match &self.data_dir {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        println!("StorageEngine: Flushing {} keys to '{}'", self.data.len(), dir.display());
+        let content = serde_json::to_vec(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let temp_path = dir.join(DATA_TEMP_FILE_NAME);
+        let mut temp_file = File::create(&temp_path)?;
+        temp_file.write_all(&content)?;
+        temp_file.sync_all()?;
+        drop(temp_file);
 
-    pub fn flush_to_disk(&self) {
-        // TODO: 实现将内存中的更改刷新到磁盘逻辑
-        println!("StorageEngine: Flushing data to disk (Not yet implemented)");
+        fs::rename(&temp_path, dir.join(DATA_FILE_NAME))?;
+        Ok(sync_dir(dir)?)
     }
 }
 
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// rename 之后对目录 fsync，确保新的目录项本身也已落盘
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
+    // 为每个测试生成独立的临时数据目录
+    pub(crate) fn temp_data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("skdb_test_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
     #[test]
     fn test_write_and_read() {
         let mut engine = StorageEngine::new();
@@ -133,4 +639,181 @@ mod tests {
         assert_eq!(engine.read(&key1), Some(value1));
         assert_eq!(engine.read(&key2), None); // key2 在快照之后添加，回滚后应不存在
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_flush_and_reopen() {
+        let dir = temp_data_dir("flush_and_reopen");
+        {
+            let mut engine = StorageEngine::open(&dir).unwrap();
+            engine.write("k1".to_string(), "v1".to_string());
+            engine.write("k1".to_string(), "v2".to_string());
+            engine.write("k2".to_string(), "other".to_string());
+            engine.flush_to_disk().unwrap();
+        } // 丢弃引擎，模拟进程重启
+
+        let engine = StorageEngine::open(&dir).unwrap();
+        assert_eq!(engine.read(&"k1".to_string()), Some("v2".to_string()));
+        assert_eq!(engine.get_version(&"k1".to_string(), 0), Some("v1".to_string()));
+        assert_eq!(engine.read(&"k2".to_string()), Some("other".to_string()));
+        assert!(!dir.join(DATA_TEMP_FILE_NAME).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_from_missing_dir_fails() {
+        let dir = temp_data_dir("missing_dir");
+        let err = StorageEngine::load_from_disk(&dir).err().unwrap();
+        assert!(matches!(err, Error::Io { kind: io::ErrorKind::NotFound, .. }));
+    }
+
+    #[test]
+    fn test_read_at_commit_timestamp() {
+        let mut engine = StorageEngine::new();
+        let key = "mvcc_key".to_string();
+        let ts1 = engine.commit_writes([(key.clone(), Some("v1".to_string()))]);
+        let ts2 = engine.commit_writes([(key.clone(), Some("v2".to_string()))]);
+        let ts3 = engine.commit_writes([(key.clone(), None)]);
+
+        assert_eq!(engine.read_at(&key, ts1 - 1), None);
+        assert_eq!(engine.read_at(&key, ts1), Some("v1".to_string()));
+        assert_eq!(engine.read_at(&key, ts2), Some("v2".to_string()));
+        assert_eq!(engine.read_at(&key, ts3), None);
+        assert_eq!(engine.read(&key), None);
+    }
+
+    #[test]
+    fn test_expired_values_are_invisible_and_survive_reload() {
+        let dir = temp_data_dir("ttl_reload");
+        {
+            let mut engine = StorageEngine::open(&dir).unwrap();
+            engine.write_with_ttl("session".to_string(), "token".to_string(), Some(Duration::from_secs(3600)));
+            engine.write("old".to_string(), "v1".to_string());
+            let before_expired = engine.last_commit_ts();
+            engine.write_with_ttl("old".to_string(), "v2".to_string(), Some(Duration::ZERO));
+            assert_eq!(engine.read(&"old".to_string()), None);
+            assert_eq!(engine.read_at(&"old".to_string(), before_expired), Some("v1".to_string()));
+            assert_eq!(engine.expired_keys(), vec!["old".to_string()]);
+            assert!(engine.scan_prefix("old").is_empty());
+            engine.flush_to_disk().unwrap();
+        }
+
+        let engine = StorageEngine::open(&dir).unwrap();
+        assert_eq!(engine.read(&"session".to_string()), Some("token".to_string()));
+        assert!(engine.history(&"session".to_string())[0].expires_at.is_some());
+        assert_eq!(engine.read(&"old".to_string()), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compare_and_swap() {
+        let mut engine = StorageEngine::new();
+        let key = "cas_key".to_string();
+        assert_eq!(engine.compare_and_swap(&key, None, Some("v1".to_string())), Ok(()));
+        assert_eq!(engine.compare_and_swap(&key, None, Some("v2".to_string())), Err(Some("v1".to_string())));
+        assert_eq!(engine.compare_and_swap(&key, Some("v1"), Some("v2".to_string())), Ok(()));
+        assert_eq!(engine.compare_and_swap(&key, Some("v2"), None), Ok(()));
+        assert_eq!(engine.read(&key), None);
+        assert_eq!(engine.compare_and_swap(&key, Some("v2"), None), Err(None));
+    }
+
+    #[test]
+    fn test_history_includes_tombstones() {
+        let mut engine = StorageEngine::new();
+        let key = "history_key".to_string();
+        engine.write(key.clone(), "v1".to_string());
+        engine.delete(&key);
+        assert_eq!(engine.delete(&key), None); // 已删除的键不会再写入删除标记
+        engine.write(key.clone(), "v2".to_string());
+
+        let history: Vec<(Option<String>, u64)> =
+            engine.history(&key).into_iter().map(|version| (version.value, version.commit_ts)).collect();
+        assert_eq!(history, vec![(Some("v1".to_string()), 1), (None, 2), (Some("v2".to_string()), 3)]);
+        assert!(engine.history(&"missing".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_writes_to_restore_named_snapshot() {
+        let mut engine = StorageEngine::new();
+        engine.write("kept".to_string(), "v1".to_string());
+        engine.write("changed".to_string(), "before".to_string());
+        assert!(engine.add_named_snapshot("snap".to_string(), engine.last_commit_ts()));
+        assert!(!engine.add_named_snapshot("snap".to_string(), 0));
+
+        engine.write("changed".to_string(), "after".to_string());
+        engine.write("added".to_string(), "new".to_string());
+        let current_ts = engine.last_commit_ts();
+        engine.write("later".to_string(), "ignored".to_string());
+
+        let ts = engine.named_snapshot("snap").unwrap();
+        assert_eq!(
+            engine.writes_to_restore(ts, current_ts),
+            vec![("added".to_string(), None), ("changed".to_string(), Some("before".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_collect_garbage_respects_retention_and_pins() {
+        let mut engine = StorageEngine::new();
+        let key = "hot".to_string();
+        for i in 1..=5 {
+            engine.write(key.clone(), format!("v{}", i)); // 提交时间戳 1..=5
+        }
+        engine.add_named_snapshot("snap".to_string(), 2);
+        engine.write("gone".to_string(), "x".to_string()); // ts 6
+        engine.delete(&"gone".to_string()); // ts 7
+
+        let policy = RetentionPolicy { keep_last: 2, keep_newer_than: None };
+        let stats = engine.collect_garbage(&policy, &[3]);
+        // v1 未被引用，被回收；v2 被命名快照引用，v3 被活动事务引用，v4 属于最近 2 个版本
+        let kept: Vec<u64> = engine.history(&key).iter().map(|version| version.commit_ts).collect();
+        assert_eq!(kept, vec![2, 3, 4, 5]);
+        assert_eq!(engine.read_at(&key, 2), Some("v2".to_string()));
+        assert_eq!(stats, GcStats { versions_removed: 1, keys_removed: 0, bytes_reclaimed: 3 + 2 });
+        assert_eq!(engine.oldest_readable_ts(), 2);
+
+        // 引用消失后再次回收；"gone" 的旧值被回收后只剩删除标记，整个键被移除
+        engine.remove_named_snapshot("snap");
+        let policy = RetentionPolicy { keep_last: 1, keep_newer_than: None };
+        let stats = engine.collect_garbage(&policy, &[]);
+        assert_eq!(stats.versions_removed, 4);
+        assert_eq!(stats.keys_removed, 1);
+        assert_eq!(engine.history(&key).len(), 1);
+        assert!(engine.history(&"gone".to_string()).is_empty());
+        assert_eq!(engine.oldest_readable_ts(), 7);
+
+        // 按时间保留时，刚提交的版本都不会被回收
+        engine.write(key.clone(), "v6".to_string());
+        engine.write(key.clone(), "v7".to_string());
+        let policy = RetentionPolicy { keep_last: 1, keep_newer_than: Some(Duration::from_secs(3600)) };
+        assert_eq!(engine.collect_garbage(&policy, &[]).versions_removed, 0);
+    }
+
+    #[test]
+    fn test_scan_and_scan_prefix() {
+        let mut engine = StorageEngine::new();
+        for key in ["user:2", "user:1", "order:1", "user:3", "userx"] {
+            engine.write(key.to_string(), format!("{}-value", key));
+        }
+        let snapshot_ts = engine.last_commit_ts();
+        engine.delete(&"user:2".to_string());
+
+        let keys = |pairs: Vec<(String, String)>| pairs.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys(engine.scan("user:", None, 10)), vec!["user:1", "user:3", "userx"]);
+        assert_eq!(keys(engine.scan("order:1", Some("user:3"), 10)), vec!["order:1", "user:1"]);
+        assert_eq!(keys(engine.scan("", None, 2)), vec!["order:1", "user:1"]);
+        assert_eq!(keys(engine.scan_prefix("user:")), vec!["user:1", "user:3"]);
+        assert!(engine.scan_prefix("nothing").is_empty());
+
+        // 旧快照中仍能看到被删除的键
+        let range = KeyRange::prefix("user:");
+        assert_eq!(engine.scan_at(&range, snapshot_ts).count(), 3);
+
+        // 快照之后的变更只有 user:2 的删除标记
+        let changes = engine.versions_since(&range, snapshot_ts);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].0.as_str(), changes[0].1.value.clone()), ("user:2", None));
+        assert!(KeyRange::single("user:1").contains("user:1"));
+        assert!(!KeyRange::single("user:1").contains("user:10"));
+        assert!(range.contains("user:9") && !range.contains("userx"));
+    }
+}
@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
 // --- Data Structures ---
 
@@ -6,11 +8,108 @@ use std::collections::HashMap;
 pub enum Value {
     String(String),
     Integer(i64),
+    Bool(bool),
+    Float(f64),
+    Date(Date),
+    DateTime(DateTime),
     Tuple(Vec<Value>),
     Reference { type_name: String, key: Box<Value> }, // For references like c::config
     Null,
 }
 
+// A calendar date, written YYYY-MM-DD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+// A date and time of day without a time zone, written YYYY-MM-DDTHH:MM:SS
+// (a space instead of the 'T' is accepted when parsing)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+// Parses exactly `len` ASCII digits
+fn parse_digits(s: &str, len: usize) -> Option<u16> {
+    if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+impl Date {
+    pub fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('-');
+        let (Some(year), Some(month), Some(day), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("Invalid date '{}', expected YYYY-MM-DD", s));
+        };
+        let (Some(year), Some(month), Some(day)) = (parse_digits(year, 4), parse_digits(month, 2), parse_digits(day, 2)) else {
+            return Err(format!("Invalid date '{}', expected YYYY-MM-DD", s));
+        };
+        let (month, day) = (month as u8, day as u8);
+        if !(1..=12).contains(&month) {
+            return Err(format!("Invalid date '{}': month must be between 01 and 12", s));
+        }
+        if day == 0 || day > Date::days_in_month(year, month) {
+            return Err(format!("Invalid date '{}': day is out of range for the month", s));
+        }
+        Ok(Date { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid datetime '{}', expected YYYY-MM-DDTHH:MM:SS", s);
+        let (date, time) = s.split_once(['T', ' ']).ok_or_else(invalid)?;
+        let date: Date = date.parse()?;
+        let mut parts = time.split(':');
+        let (Some(hour), Some(minute), Some(second), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let (Some(hour), Some(minute), Some(second)) = (parse_digits(hour, 2), parse_digits(minute, 2), parse_digits(second, 2)) else {
+            return Err(invalid());
+        };
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(format!("Invalid datetime '{}': time is out of range", s));
+        }
+        Ok(DateTime { date, hour: hour as u8, minute: minute as u8, second: second as u8 })
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{:02}:{:02}:{:02}", self.date, self.hour, self.minute, self.second)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct HeaderField {
     pub name: String, // Made public for parser module
@@ -229,4 +328,20 @@ impl Table {
     }
 }
 
-pub type DslRoot = HashMap<String, Table>;
\ No newline at end of file
+pub type DslRoot = HashMap<String, Table>;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates_are_validated() {
+        assert_eq!("2000-02-29".parse::<Date>().unwrap().to_string(), "2000-02-29");
+        assert!("1900-02-29".parse::<Date>().is_err());
+        assert!("2025-13-01".parse::<Date>().is_err());
+        assert!("2025-1-01".parse::<Date>().is_err());
+        let datetime: DateTime = "2025-06-30 08:05:00".parse().unwrap();
+        assert_eq!(datetime.to_string(), "2025-06-30T08:05:00");
+        assert!("2025-06-30T24:00:00".parse::<DateTime>().is_err());
+        assert!("2025-06-30T08:05".parse::<DateTime>().is_err());
+    }
+}
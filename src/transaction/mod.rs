@@ -1,101 +1,1006 @@
 // src/transaction/mod.rs
 
-// 引入 StorageEngine
-use crate::storage::StorageEngine;
+// 引入 StorageEngine 和 LogMgr
+use crate::error::{Error, Resource};
+use crate::log::{LogMgr, LogRecord};
+use crate::storage::{expires_at_after, unix_millis, GcStats, KeyRange, RetentionPolicy, StorageEngine, Version};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 
+// 数据目录中的预写日志文件名
+const WAL_FILE_NAME: &str = "wal.log";
+// 日志超过该大小时，提交后做一次检查点 (刷新数据文件并清空日志)
+const CHECKPOINT_THRESHOLD_BYTES: u64 = 4 * 1024 * 1024;
+
+// 命名快照的描述信息
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotInfo {
+    pub snapshot_id: String,
+    pub commit_ts: u64, // 快照包含所有在此时间戳 (含) 之前提交的数据
+}
+
+// 单个活动事务。模式、隔离级别、截止时间和快照在开始时确定，之后不再改变；
+// 可变的部分由事务自己的锁保护，同一个事务上的操作互相串行，不同事务之间互不阻塞
+struct Transaction {
+    mode: TransactionMode,
+    isolation: IsolationLevel,
+    deadline: Option<Instant>, // 超过这个时间仍未结束的事务会被自动回滚
+    start_ts: u64, // 事务开始时最后一次提交的时间戳，事务只能读到此时已提交的数据 (快照隔离)
+    state: Mutex<TransactionState>,
+}
+
+struct TransactionState {
+    // 已提交或已回滚。其他请求可能在事务结束前已经拿到了它，之后在它上面的操作都必须失败
+    finished: bool,
+    pending_writes: BTreeMap<String, Option<String>>, // 该事务尚未提交的写入 (键 -> 新值，None 表示删除)，有序以便与扫描合并
+    expirations: HashMap<String, u64>, // 带 TTL 的写入的过期时间 (Unix 毫秒)
+    savepoints: Vec<Savepoint>, // 按创建顺序排列，同名时以最近的为准
+    // 可串行化读写事务的读集，提交时验证
+    // 只读事务读取的是一个已提交状态的快照，本身就可以串行化，不需要记录
+    read_set: Option<ReadSet>,
+}
+
+// 事务读过的键和扫描过的范围 (包括读到不存在的键，以防止幻读)
+#[derive(Default)]
+struct ReadSet {
+    keys: BTreeSet<String>,
+    ranges: Vec<KeyRange>,
+}
+
+// 保存点：创建时事务写集的副本，回滚到保存点时恢复这份副本
+struct Savepoint {
+    name: String,
+    pending_writes: BTreeMap<String, Option<String>>,
+    expirations: HashMap<String, u64>,
+}
+
+// TxnMgr 的所有方法都只需要 &self，可以在多个线程之间共享。
+// 加锁顺序固定为 active_transactions -> 单个事务的状态 -> log_mgr -> storage_engine，
+// 其余的锁 (正在提交的事务、超时记录、保留策略、默认超时、指标) 持有期间不会再获取其他锁
 pub struct TxnMgr {
-    storage_engine: StorageEngine, // 添加 storage_engine 成员
-    current_transaction_id: Option<u64>, // 用于跟踪当前事务
-    pending_writes: std::collections::HashMap<String, Option<String>>, // 跟踪当前事务期间所做的更改
+    storage_engine: RwLock<StorageEngine>, // 读取共享读锁，只有应用提交、回收等修改才获取写锁
+    // 预写日志，None 时每次提交直接刷新数据文件
+    // 这把锁同时是提交锁：提交按顺序分配时间戳、写日志并应用，数据文件也只在持有它时刷新
+    log_mgr: Mutex<Option<LogMgr>>,
+    active_transactions: RwLock<HashMap<u64, Arc<Transaction>>>, // 所有活动事务，按事务 ID 索引
+    // 已从活动事务中移除但还没有提交完成的事务的开始时间戳，按事务 ID 索引。
+    // 回收历史版本时与活动事务一样固定它们的快照，否则提交验证依赖的删除标记可能先被回收
+    committing: Mutex<HashMap<u64, u64>>,
+    retention_policy: RwLock<RetentionPolicy>, // 历史版本的保留策略
+    gc_metrics: Mutex<GcMetrics>,
+    change_feed: broadcast::Sender<ChangeEvent>, // 每次提交后广播该事务的写入，只包含已提交的数据
+    default_timeout: RwLock<Option<Duration>>, // 新事务的默认超时时间，None 表示不超时
+    timed_out_transactions: Mutex<BTreeSet<u64>>, // 最近因超时被回滚的事务，用于返回明确的错误
+}
+
+// 锁被污染只说明另一个线程在持有锁时 panic 了；这里保护的数据在每次修改后都是一致的，可以继续使用
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn append_log(log_mgr: &mut Option<LogMgr>, record: &LogRecord) -> Result<(), Error> {
+    match log_mgr.as_mut() {
+        Some(log_mgr) => log_mgr.log_operation(record),
+        None => Ok(()),
+    }
+}
+
+// 追加 Rollback 记录。恢复时没有 Commit 记录的事务本来就会被丢弃，这条记录只是让日志更完整，
+// 因此写入失败不影响回滚本身，只打印出来
+fn append_rollback(log_mgr: &mut Option<LogMgr>, transaction_id: u64) {
+    if let Err(e) = append_log(log_mgr, &LogRecord::Rollback { transaction_id }) {
+        println!("TxnMgr: Error - Failed to log rollback of transaction ID {}: {}", transaction_id, e);
+    }
+}
+
+// 事务中最近一个同名保存点的位置
+fn savepoint_position(state: &TransactionState, transaction_id: u64, name: &str) -> Result<usize, Error> {
+    state
+        .savepoints
+        .iter()
+        .rposition(|savepoint| savepoint.name == name)
+        .ok_or_else(|| Error::NotFound(Resource::Savepoint { transaction_id, name: name.to_string() }))
+}
+
+impl Transaction {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| deadline <= now)
+    }
+
+    // 读取已提交数据时使用的时间戳：ReadCommitted 为最新提交，其他级别为事务开始时的快照
+    fn read_ts(&self, storage: &StorageEngine) -> u64 {
+        match self.isolation {
+            IsolationLevel::ReadCommitted => storage.last_commit_ts(),
+            IsolationLevel::Snapshot | IsolationLevel::Serializable => self.start_ts,
+        }
+    }
+}
+
+// 最多记住多少个因超时被回滚的事务 ID
+const MAX_REMEMBERED_TIMEOUTS: usize = 10_000;
+// 变更流中缓冲的事件数，订阅者落后超过这个数量时需要从最后收到的提交时间戳重新订阅
+const CHANGE_FEED_CAPACITY: usize = 1024;
+
+// 事务模式：只读事务中的写入会被拒绝
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionMode {
+    #[default]
+    ReadWrite,
+    ReadOnly,
+}
+
+// 隔离级别。所有级别的写入都使用先提交者胜出的写写冲突检测，区别在于读取：
+// ReadCommitted 每次读取都看到最新提交的数据，Snapshot 始终读取事务开始时的快照，
+// Serializable 在快照读取的基础上还保证可串行化
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IsolationLevel {
+    ReadCommitted,
+    #[default]
+    Snapshot,
+    Serializable,
+}
+
+// 开始事务时的选项，通过 TxnMgr::default_begin_options 获得默认值
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeginOptions {
+    pub mode: TransactionMode,
+    pub isolation: IsolationLevel,
+    pub timeout: Option<Duration>, // None 表示该事务永不超时
+}
+
+// 一次提交中的一个写入或删除
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KeyChange {
+    pub key: String,
+    pub value: Option<String>, // None 表示删除
+}
+
+// 一次提交产生的全部变更，由变更流推送给订阅者；同一次提交的变更总是一起推送，恢复订阅时不会只收到一部分
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ChangeEvent {
+    pub commit_ts: u64, // 提交时间戳，也是恢复订阅时使用的序号
+    pub changes: Vec<KeyChange>,
+}
+
+impl ChangeEvent {
+    // 只保留范围内的变更；没有剩余变更时返回 None
+    pub fn filter(&self, range: &KeyRange) -> Option<ChangeEvent> {
+        let changes: Vec<KeyChange> = self.changes.iter().filter(|change| range.contains(&change.key)).cloned().collect();
+        (!changes.is_empty()).then_some(ChangeEvent { commit_ts: self.commit_ts, changes })
+    }
+}
+
+// 垃圾回收的累计指标 (不持久化，进程重启后清零)
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct GcMetrics {
+    pub runs: u64,
+    pub versions_removed: u64,
+    pub keys_removed: u64,
+    pub bytes_reclaimed: u64,
+    pub last_run: Option<GcStats>,
+}
+
+impl Default for TxnMgr {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TxnMgr {
     pub fn new() -> Self {
+        Self::with_storage(StorageEngine::new()) // 初始化纯内存 storage_engine
+    }
+
+    // 使用已打开的 StorageEngine (例如从磁盘加载的) 创建 TxnMgr
+    pub fn with_storage(storage_engine: StorageEngine) -> Self {
         println!("TxnMgr initialized.");
         TxnMgr {
-            storage_engine: StorageEngine::new(), // 初始化 storage_engine
-            current_transaction_id: None,
-            pending_writes: std::collections::HashMap::new(),
+            storage_engine: RwLock::new(storage_engine),
+            log_mgr: Mutex::new(None),
+            active_transactions: RwLock::new(HashMap::new()),
+            committing: Mutex::new(HashMap::new()),
+            retention_policy: RwLock::new(RetentionPolicy::default()),
+            gc_metrics: Mutex::new(GcMetrics::default()),
+            change_feed: broadcast::channel(CHANGE_FEED_CAPACITY).0,
+            default_timeout: RwLock::new(None),
+            timed_out_transactions: Mutex::new(BTreeSet::new()),
         }
     }
 
-    pub fn begin_transaction(&mut self) -> u64 {
-        // TODO: 实现更完善的事务 ID 生成逻辑
-        let transaction_id = self.current_transaction_id.map_or(1, |id| id + 1);
-        self.current_transaction_id = Some(transaction_id);
-        self.pending_writes.clear(); // 清除任何旧的 pending_writes
-        println!("TxnMgr: Beginning transaction ID: {}", transaction_id);
-        transaction_id
+    // 打开数据目录：加载数据文件，用预写日志恢复已提交的事务，然后做一次检查点
+    pub fn open<P: AsRef<Path>>(data_dir: P) -> Result<Self, Error> {
+        let data_dir = data_dir.as_ref();
+        let mut storage_engine = StorageEngine::open(data_dir)?;
+        let mut log_mgr = LogMgr::open(data_dir.join(WAL_FILE_NAME))?;
+        log_mgr.recover(&mut storage_engine)?;
+
+        let mut txn_mgr = Self::with_storage(storage_engine);
+        txn_mgr.log_mgr = Mutex::new(Some(log_mgr));
+        txn_mgr.checkpoint()?;
+        Ok(txn_mgr)
     }
 
-    // 这个方法可以用来代表事务中的一个操作
-    pub fn execute_write_operation(&mut self, transaction_id: u64, key: String, value: String) {
-        if self.current_transaction_id != Some(transaction_id) {
-            // 或者可以返回一个错误
-            println!("TxnMgr: Error - Operation on inactive or incorrect transaction ID: {}", transaction_id);
-            return;
+    fn storage(&self) -> RwLockReadGuard<'_, StorageEngine> {
+        read(&self.storage_engine)
+    }
+
+    fn storage_mut(&self) -> RwLockWriteGuard<'_, StorageEngine> {
+        write(&self.storage_engine)
+    }
+
+    // 将内存中已提交的数据刷新到数据文件，之后日志中的记录不再需要
+    // 事务的写入在提交时才写入日志，活动事务在日志中只有 Begin 记录，清空日志不会丢失它们的写入；
+    // 提交从写日志到应用写入都持有 log_mgr 锁，检查点也先获取它，因此不会看到写了日志但还没有应用的提交
+    pub fn checkpoint(&self) -> Result<(), Error> {
+        let mut log_mgr = lock(&self.log_mgr);
+        println!("TxnMgr: Checkpointing...");
+        self.storage().flush_to_disk()?;
+        if let Some(log_mgr) = log_mgr.as_mut() {
+            log_mgr.truncate()?;
         }
-        println!("TxnMgr: Executing write operation for transaction ID: {}. Key: {}, Value: {}", transaction_id, key, value);
-        
-        // 在实际写入 StorageEngine 之前，如果键已存在，则读取其当前值并将其存储在 pending_writes 中
-        if !self.pending_writes.contains_key(&key) { // 只记录第一次修改前的值
-            let original_value = self.storage_engine.read(&key);
-            self.pending_writes.insert(key.clone(), original_value);
+        Ok(())
+    }
+
+    fn log(&self, record: LogRecord) -> Result<(), Error> {
+        append_log(&mut lock(&self.log_mgr), &record)
+    }
+
+    // 区分从未存在的事务和已经结束的事务
+    fn inactive_error(&self, transaction_id: u64) -> Error {
+        if lock(&self.timed_out_transactions).contains(&transaction_id) {
+            return Error::TxnTimedOut(transaction_id);
+        }
+        let last_transaction_id = self.storage().last_transaction_id();
+        if transaction_id != 0 && transaction_id <= last_transaction_id {
+            Error::TxnNotActive(transaction_id)
+        } else {
+            Error::NotFound(Resource::Transaction(transaction_id))
+        }
+    }
+
+    // 获取活动事务；已超过截止时间的事务在这里被回滚
+    fn transaction(&self, transaction_id: u64) -> Result<Arc<Transaction>, Error> {
+        let transaction = read(&self.active_transactions).get(&transaction_id).cloned();
+        match transaction {
+            Some(transaction) if transaction.is_expired(Instant::now()) => {
+                self.abort_timed_out(transaction_id);
+                Err(Error::TxnTimedOut(transaction_id))
+            }
+            Some(transaction) => Ok(transaction),
+            None => {
+                let err = self.inactive_error(transaction_id);
+                println!("TxnMgr: Error - {}", err);
+                Err(err)
+            }
+        }
+    }
+
+    // 锁住事务的状态；事务在等待锁的期间结束了时返回与已结束的事务相同的错误
+    fn transaction_state<'a>(
+        &self,
+        transaction_id: u64,
+        transaction: &'a Transaction,
+    ) -> Result<MutexGuard<'a, TransactionState>, Error> {
+        let state = lock(&transaction.state);
+        if state.finished {
+            return Err(self.inactive_error(transaction_id));
+        }
+        Ok(state)
+    }
+
+    // 获取可以写入的活动事务，只读事务返回 ReadOnlyTransaction
+    fn writable_transaction(&self, transaction_id: u64) -> Result<Arc<Transaction>, Error> {
+        let transaction = self.transaction(transaction_id)?;
+        if transaction.mode == TransactionMode::ReadOnly {
+            println!("TxnMgr: Error - Write rejected in read-only transaction ID: {}", transaction_id);
+            return Err(Error::TxnReadOnly(transaction_id));
         }
+        Ok(transaction)
+    }
 
-        // 调用 self.storage_engine.write(key, value) 来实际存储数据
-        self.storage_engine.write(key, value);
+    // 从活动事务中移除并标记为已结束，返回它；已经不是活动事务时返回 None
+    fn finish(&self, transaction_id: u64) -> Option<Arc<Transaction>> {
+        let transaction = write(&self.active_transactions).remove(&transaction_id)?;
+        lock(&transaction.state).finished = true;
+        Some(transaction)
     }
 
-    pub fn commit_transaction(&mut self, transaction_id: u64) {
-        if self.current_transaction_id != Some(transaction_id) {
-            println!("TxnMgr: Error - Attempting to commit inactive or incorrect transaction ID: {}", transaction_id);
+    // 回滚一个已超时的事务，并记住它的 ID，以便之后对它的操作返回明确的超时错误
+    // 与回滚一样标记为已结束，已经拿到该事务的其他请求 (例如正在等待它的锁) 之后的操作都会失败
+    fn abort_timed_out(&self, transaction_id: u64) {
+        if self.finish(transaction_id).is_none() {
             return;
         }
+        {
+            let mut timed_out = lock(&self.timed_out_transactions);
+            timed_out.insert(transaction_id);
+            while timed_out.len() > MAX_REMEMBERED_TIMEOUTS {
+                timed_out.pop_first();
+            }
+        }
+        println!("TxnMgr: Transaction ID {} timed out, rolling back", transaction_id);
+        append_rollback(&mut lock(&self.log_mgr), transaction_id);
+    }
+
+    // 回滚所有已超过截止时间的事务 (服务器中由后台任务定期调用)，返回被回滚的事务 ID
+    pub fn reap_expired_transactions(&self) -> Vec<u64> {
+        let now = Instant::now();
+        let mut expired: Vec<u64> = read(&self.active_transactions)
+            .iter()
+            .filter(|(_, transaction)| transaction.is_expired(now))
+            .map(|(transaction_id, _)| *transaction_id)
+            .collect();
+        expired.sort();
+        for transaction_id in &expired {
+            self.abort_timed_out(*transaction_id);
+        }
+        expired
+    }
+
+    pub fn default_timeout(&self) -> Option<Duration> {
+        *read(&self.default_timeout)
+    }
+
+    // 设置新事务的默认超时时间，None 表示默认不超时；不影响已经开始的事务
+    pub fn set_default_timeout(&self, timeout: Option<Duration>) {
+        println!("TxnMgr: Default transaction timeout set to {:?}", timeout);
+        *write(&self.default_timeout) = timeout;
+    }
+
+    pub fn is_active(&self, transaction_id: u64) -> bool {
+        read(&self.active_transactions).contains_key(&transaction_id)
+    }
+
+    // 默认的开始选项：读写、快照隔离、使用默认超时时间
+    pub fn default_begin_options(&self) -> BeginOptions {
+        BeginOptions {
+            mode: TransactionMode::ReadWrite,
+            isolation: IsolationLevel::Snapshot,
+            timeout: self.default_timeout(),
+        }
+    }
+
+    pub fn begin_transaction(&self) -> Result<u64, Error> {
+        self.begin_transaction_with_options(self.default_begin_options())
+    }
+
+    // 开始一个事务，timeout 为 None 时该事务永不超时
+    pub fn begin_transaction_with_timeout(&self, timeout: Option<Duration>) -> Result<u64, Error> {
+        self.begin_transaction_with_options(BeginOptions { timeout, ..self.default_begin_options() })
+    }
+
+    // 先写 Begin 记录再登记事务，写日志失败时事务不会开始 (消耗掉的事务 ID 不再使用)
+    pub fn begin_transaction_with_options(&self, options: BeginOptions) -> Result<u64, Error> {
+        // 在登记事务之前不能有垃圾回收，否则它的快照可能在被固定之前就被回收
+        let mut active = write(&self.active_transactions);
+        let (transaction_id, start_ts) = {
+            // 事务 ID 单调递增且从不复用 (最后分配的 ID 随数据文件一起持久化)
+            let mut storage = self.storage_mut();
+            let transaction_id = storage.last_transaction_id() + 1;
+            storage.observe_transaction_id(transaction_id);
+            (transaction_id, storage.last_commit_ts())
+        };
+        self.log(LogRecord::Begin { transaction_id })?;
+        let tracks_reads = options.isolation == IsolationLevel::Serializable && options.mode == TransactionMode::ReadWrite;
+        active.insert(transaction_id, Arc::new(Transaction {
+            mode: options.mode,
+            isolation: options.isolation,
+            deadline: options.timeout.map(|timeout| Instant::now() + timeout),
+            start_ts,
+            state: Mutex::new(TransactionState {
+                finished: false,
+                pending_writes: BTreeMap::new(),
+                expirations: HashMap::new(),
+                savepoints: Vec::new(),
+                read_set: tracks_reads.then(ReadSet::default),
+            }),
+        }));
+        println!(
+            "TxnMgr: Beginning transaction ID: {} ({} active, {:?})",
+            transaction_id,
+            active.len(),
+            options
+        );
+        Ok(transaction_id)
+    }
+
+    // 这个方法可以用来代表事务中的一个操作
+    // 写入先缓存在事务的 pending_writes 中，提交时才写入 StorageEngine，其他事务看不到未提交的写入
+    pub fn execute_write_operation(&self, transaction_id: u64, key: String, value: String) -> Result<(), Error> {
+        self.execute_write_operation_with_ttl(transaction_id, key, value, None)
+    }
+
+    // 写入一个在 ttl 之后过期的值，过期时间从写入时开始计算
+    pub fn execute_write_operation_with_ttl(
+        &self,
+        transaction_id: u64,
+        key: String,
+        value: String,
+        ttl: Option<Duration>,
+    ) -> Result<(), Error> {
+        let transaction = self.writable_transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        println!(
+            "TxnMgr: Executing write operation for transaction ID: {}. Key: {}, Value: {}, TTL: {:?}",
+            transaction_id, key, value, ttl
+        );
+        let expires_at = ttl.map(expires_at_after);
+        // 写入只进入写集，提交时才与 Commit 记录一起写入日志
+        state.pending_writes.insert(key.clone(), Some(value));
+        match expires_at {
+            Some(expires_at) => state.expirations.insert(key, expires_at),
+            None => state.expirations.remove(&key),
+        };
+        Ok(())
+    }
+
+    // 条件写入：事务中看到的当前值等于 expected (None 表示不存在) 时写入 new_value (None 表示删除)
+    // 检查基于事务快照，若其他事务在此之后修改了该键，提交时的写写冲突检测会中止本事务，因此整体仍是原子的
+    pub fn compare_and_swap(
+        &self,
+        transaction_id: u64,
+        key: String,
+        expected: Option<&str>,
+        new_value: Option<String>,
+    ) -> Result<(), Error> {
+        let actual = self.read_operation(transaction_id, &key)?;
+        if actual.as_deref() != expected {
+            println!("TxnMgr: Precondition failed for key '{}' in transaction ID: {}", key, transaction_id);
+            return Err(Error::PreconditionFailed { key, actual });
+        }
+        match new_value {
+            Some(value) => self.execute_write_operation(transaction_id, key, value),
+            None => self.delete(transaction_id, key).map(|_| ()),
+        }
+    }
+
+    // 把键的整数值加上 delta (不存在时视为 0)，返回新值
+    pub fn increment(&self, transaction_id: u64, key: String, delta: i64) -> Result<i64, Error> {
+        let current = match self.read_operation(transaction_id, &key)? {
+            Some(value) => value.trim().parse::<i64>().map_err(|_| Error::TypeMismatch {
+                key: key.clone(),
+                reason: format!("'{}' is not an integer", value),
+            })?,
+            None => 0,
+        };
+        let updated = current.checked_add(delta).ok_or_else(|| Error::TypeMismatch {
+            key: key.clone(),
+            reason: format!("{} + {} overflows", current, delta),
+        })?;
+        self.execute_write_operation(transaction_id, key, updated.to_string())?;
+        Ok(updated)
+    }
+
+    pub fn commit_transaction(&self, transaction_id: u64) -> Result<(), Error> {
+        let transaction = self.start_commit(transaction_id)?;
+        let result = self.commit_removed(transaction_id, &transaction);
+        lock(&self.committing).remove(&transaction_id);
+        if result? > CHECKPOINT_THRESHOLD_BYTES {
+            if let Err(e) = self.checkpoint() {
+                println!("TxnMgr: Error - Checkpoint failed: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    // 把事务从活动事务移到正在提交的事务中，提交完成后调用者负责从 committing 中移除
+    // 两步在同一个 active_transactions 写锁下完成，回收历史版本时总能看到这个事务的快照
+    fn start_commit(&self, transaction_id: u64) -> Result<Arc<Transaction>, Error> {
+        self.transaction(transaction_id)?;
+        let mut active = write(&self.active_transactions);
+        let transaction = active.remove(&transaction_id).ok_or_else(|| self.inactive_error(transaction_id))?;
+        lock(&self.committing).insert(transaction_id, transaction.start_ts);
+        Ok(transaction)
+    }
+
+    // 提交一个已经从活动事务中移除的事务，返回提交后日志的大小
+    fn commit_removed(&self, transaction_id: u64, transaction: &Transaction) -> Result<u64, Error> {
+        let mut state = lock(&transaction.state);
+        if state.finished {
+            return Err(self.inactive_error(transaction_id));
+        }
+        state.finished = true;
         println!("TxnMgr: Committing transaction ID: {}", transaction_id);
-        self.pending_writes.clear(); // 成功提交后清除 pending_writes
-        self.current_transaction_id = None; // 事务结束后重置
+
+        // 没有写入的事务 (包括所有只读事务) 读到的是一个已提交状态的快照，不需要验证，
+        // 也不需要新的提交时间戳和日志同步。它们不改变存储，也不记录提交时间戳 (日志中同样没有它们的 Commit 记录)
+        if state.pending_writes.is_empty() {
+            println!("TxnMgr: Transaction ID {} committed without writes", transaction_id);
+            return Ok(0);
+        }
+
+        // 提交锁：从这里开始到应用写入，不会有其他提交
+        let mut log_mgr = lock(&self.log_mgr);
+        let (conflict, commit_ts) = {
+            let storage = self.storage();
+            // 先提交者胜出：若写集中的任一键在本事务开始后被其他事务提交过，则中止本事务
+            // 可串行化：读集中的任一键或范围在本事务开始后被其他事务提交过修改时，本事务读到的数据已经过时，
+            // 按提交顺序排列时不存在与之等价的串行执行 (例如写偏斜)，中止本事务
+            let conflict = match find_write_conflict(&storage, transaction.start_ts, &state) {
+                Some(key) => Some(Error::Conflict { transaction_id, key }),
+                None => find_read_conflict(&storage, transaction.start_ts, &state)
+                    .map(|key| Error::SerializationFailure { transaction_id, key }),
+            };
+            (conflict, storage.last_commit_ts() + 1)
+        };
+        if let Some(err) = conflict {
+            println!("TxnMgr: {}", err);
+            append_rollback(&mut log_mgr, transaction_id);
+            return Err(err);
+        }
+
+        // 以同一个提交时间戳应用该事务的全部写入
+        let mut expirations = std::mem::take(&mut state.expirations);
+        let writes: Vec<_> = std::mem::take(&mut state.pending_writes)
+            .into_iter()
+            .map(|(key, value)| {
+                let expires_at = expirations.remove(&key);
+                (key, value, expires_at)
+            })
+            .collect();
+        // 只有在有订阅者时才需要为变更流保留一份写入
+        let changes: Vec<KeyChange> = if self.change_feed.receiver_count() > 0 {
+            writes.iter().map(|(key, value, _)| KeyChange { key: key.clone(), value: value.clone() }).collect()
+        } else {
+            Vec::new()
+        };
+        // 先把全部写入和 Commit 记录一次写入日志并 fsync，再应用写入：提交只在持久化之后才对读取可见，
+        // 而等待磁盘期间读取不受阻塞。写日志或 fsync 失败时不应用写入，事务按中止处理 (此时它已经被标记为结束)
+        // 提交时间也写入日志，重启后按时间的保留策略仍然以原来的提交时间计算
+        let committed_at = unix_millis();
+        if let Some(log_mgr) = log_mgr.as_mut() {
+            let mut records: Vec<LogRecord> = writes
+                .iter()
+                .map(|(key, value, expires_at)| match value {
+                    Some(value) => {
+                        LogRecord::Write { transaction_id, key: key.clone(), value: value.clone(), expires_at: *expires_at }
+                    }
+                    None => LogRecord::Delete { transaction_id, key: key.clone() },
+                })
+                .collect();
+            records.push(LogRecord::Commit { transaction_id, commit_ts, committed_at: Some(committed_at) });
+            if let Err(e) = log_mgr.log_batch(&records).and_then(|_| log_mgr.sync()) {
+                println!("TxnMgr: Error - Failed to persist transaction ID {}: {}", transaction_id, e);
+                return Err(e);
+            }
+        }
+        {
+            let mut storage = self.storage_mut();
+            storage.commit_expiring_writes_at_time(writes, commit_ts, committed_at);
+            storage.record_transaction_commit(transaction_id, commit_ts);
+        }
+        // 没有日志时直接刷新数据文件 (纯内存引擎时为空操作)；写入此时已经可见，但要让调用者知道它没有落盘
+        if log_mgr.is_none() {
+            self.storage().flush_to_disk()?;
+        }
+        if !changes.is_empty() {
+            let _ = self.change_feed.send(ChangeEvent { commit_ts, changes });
+        }
+        println!("TxnMgr: Transaction ID {} committed at ts {}", transaction_id, commit_ts);
+        Ok(log_mgr.as_ref().and_then(|log_mgr| log_mgr.len().ok()).unwrap_or(0))
     }
 
-    pub fn rollback_transaction(&mut self, transaction_id: u64) {
-        if self.current_transaction_id != Some(transaction_id) {
-            println!("TxnMgr: Error - Attempting to rollback inactive or incorrect transaction ID: {}", transaction_id);
-            return;
+    pub fn rollback_transaction(&self, transaction_id: u64) -> Result<(), Error> {
+        self.transaction(transaction_id)?;
+        let transaction = self.finish(transaction_id).ok_or_else(|| self.inactive_error(transaction_id))?;
+        // 写入从未进入 StorageEngine，丢弃 pending_writes 即可
+        println!(
+            "TxnMgr: Rolling back transaction ID: {} (discarding {} pending write(s))",
+            transaction_id,
+            lock(&transaction.state).pending_writes.len()
+        );
+        append_rollback(&mut lock(&self.log_mgr), transaction_id);
+        Ok(())
+    }
+
+    // 在事务中创建保存点，记录当前的写集
+    pub fn create_savepoint(&self, transaction_id: u64, name: String) -> Result<(), Error> {
+        let transaction = self.transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        println!("TxnMgr: Creating savepoint '{}' in transaction ID: {}", name, transaction_id);
+        let savepoint = Savepoint {
+            name,
+            pending_writes: state.pending_writes.clone(),
+            expirations: state.expirations.clone(),
+        };
+        state.savepoints.push(savepoint);
+        Ok(())
+    }
+
+    // 撤销保存点之后的所有写入；保存点本身保留 (可以再次回滚到它)，之后创建的保存点被丢弃
+    pub fn rollback_to_savepoint(&self, transaction_id: u64, name: &str) -> Result<(), Error> {
+        let transaction = self.transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        let position = savepoint_position(&state, transaction_id, name)?;
+        println!("TxnMgr: Rolling back transaction ID: {} to savepoint '{}'", transaction_id, name);
+        state.savepoints.truncate(position + 1);
+        let savepoint = &state.savepoints[position];
+        let (pending_writes, expirations) = (savepoint.pending_writes.clone(), savepoint.expirations.clone());
+        state.pending_writes = pending_writes;
+        state.expirations = expirations;
+        Ok(())
+    }
+
+    // 释放保存点 (以及之后创建的保存点)，写入保持不变
+    pub fn release_savepoint(&self, transaction_id: u64, name: &str) -> Result<(), Error> {
+        let transaction = self.transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        let position = savepoint_position(&state, transaction_id, name)?;
+        println!("TxnMgr: Releasing savepoint '{}' in transaction ID: {}", name, transaction_id);
+        state.savepoints.truncate(position);
+        Ok(())
+    }
+
+    // 事务中的保存点名称，按创建顺序排列
+    pub fn savepoints(&self, transaction_id: u64) -> Result<Vec<String>, Error> {
+        let transaction = self.transaction(transaction_id)?;
+        let state = self.transaction_state(transaction_id, &transaction)?;
+        Ok(state.savepoints.iter().map(|savepoint| savepoint.name.clone()).collect())
+    }
+
+    // 不在事务中读取最新提交的数据
+    pub fn read_committed(&self, key: &String) -> Option<String> {
+        self.storage().read(key)
+    }
+
+    // 不在事务中扫描最新提交的数据
+    pub fn scan_committed(&self, range: &KeyRange, limit: usize) -> Vec<(String, String)> {
+        let storage = self.storage();
+        storage.scan_at(range, storage.last_commit_ts()).take(limit).collect()
+    }
+
+    // 在事务中读取：优先返回本事务自己的写入，否则返回按隔离级别可见的已提交值
+    pub fn read_operation(&self, transaction_id: u64, key: &String) -> Result<Option<String>, Error> {
+        self.read_in_transaction(transaction_id, key, true)
+    }
+
+    // 与 read_operation 相同，但不记录到可串行化事务的读集中。
+    // 只用于盲写时报告键之前是否存在：写入的内容不依赖读到的值，记录下来只会造成不必要的串行化失败
+    pub fn read_untracked(&self, transaction_id: u64, key: &String) -> Result<Option<String>, Error> {
+        self.read_in_transaction(transaction_id, key, false)
+    }
+
+    fn read_in_transaction(&self, transaction_id: u64, key: &String, track: bool) -> Result<Option<String>, Error> {
+        let transaction = self.transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        if let Some(pending) = state.pending_writes.get(key) {
+            return Ok(pending.clone());
         }
-        println!("TxnMgr: Rolling back transaction ID: {}", transaction_id);
-        for (key, original_value) in &self.pending_writes {
-            match original_value {
-                Some(val) => {
-                    println!("TxnMgr: Rolling back key '{}' to value '{}'", key, val);
-                    self.storage_engine.write(key.clone(), val.clone());
-                }
-                None => {
-                    println!("TxnMgr: Rolling back by deleting key '{}'", key);
-                    self.storage_engine.delete_data(key.clone()); // 假设 delete_data 存在于 StorageEngine
+        if let Some(read_set) = state.read_set.as_mut().filter(|_| track) {
+            read_set.keys.insert(key.clone());
+        }
+        let storage = self.storage();
+        Ok(storage.read_at(key, transaction.read_ts(&storage)))
+    }
+
+    // 按键的顺序扫描范围内的键值对，最多返回 limit 个
+    // 事务中扫描时与单键读取的可见性一致：按隔离级别可见的已提交数据，再叠加本事务尚未提交的写入
+    pub fn scan(&self, transaction_id: u64, range: &KeyRange, limit: usize) -> Result<Vec<(String, String)>, Error> {
+        let transaction = self.transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        let storage = self.storage();
+
+        let mut committed = storage.scan_at(range, transaction.read_ts(&storage)).peekable();
+        let mut pending = state
+            .pending_writes
+            .range::<str, _>((range.lower_bound(), Bound::Unbounded))
+            .take_while(|(key, _)| !range.is_past_end(key))
+            .peekable();
+        let mut results = Vec::new();
+        while results.len() < limit {
+            // 两个有序序列归并，同一个键以本事务的写入为准
+            let order = match (committed.peek(), pending.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((committed_key, _)), Some((pending_key, _))) => committed_key.as_str().cmp(pending_key.as_str()),
+            };
+            if order == Ordering::Equal {
+                committed.next();
+            }
+            let take_pending = order != Ordering::Less;
+            if take_pending {
+                let (key, value) = pending.next().unwrap();
+                if let Some(value) = value {
+                    results.push((key.clone(), value.clone()));
                 }
+            } else {
+                results.extend(committed.next());
             }
         }
-        self.pending_writes.clear();
-        self.current_transaction_id = None; // 事务结束后重置
+        drop(pending);
+        // 记录实际扫描过的部分：达到 limit 时只到最后返回的键为止，之后的键没有被读到
+        if let Some(read_set) = state.read_set.as_mut() {
+            let scanned = match results.last() {
+                Some((last, _)) if results.len() == limit => KeyRange { end: Bound::Included(last.clone()), ..range.clone() },
+                _ => range.clone(),
+            };
+            read_set.ranges.push(scanned);
+        }
+        Ok(results)
     }
 
-    // 实现 read_operation 方法
-    pub fn read_operation(&self, transaction_id: u64, key: &String) -> Option<String> {
-        // 简单的实现，直接读取，未来可以根据 transaction_id 实现更复杂的逻辑 (如MVCC)
-        // 当前版本忽略 transaction_id，直接从 storage_engine 读取
-        println!("TxnMgr: Reading key: '{}' for transaction ID: {}", key, transaction_id);
-        self.storage_engine.read(key)
+    // 时间点读取：返回在提交时间戳 ts (含) 时已提交的值
+    // 不允许读取未来的时间戳，否则同一个请求的结果会随之后的提交而改变
+    pub fn read_as_of(&self, key: &String, ts: u64) -> Result<Option<String>, Error> {
+        let storage = self.storage();
+        if ts > storage.last_commit_ts() {
+            return Err(Error::FutureTimestamp(ts));
+        }
+        if ts < storage.oldest_readable_ts() {
+            return Err(Error::HistoryPruned(ts));
+        }
+        Ok(storage.read_at(key, ts))
     }
 
-    // 保留 delete 以便将来使用
-    pub fn delete(&mut self, transaction_id: u64, key: String) {
-        if self.current_transaction_id != Some(transaction_id) {
-            println!("TxnMgr: Error - Operation on inactive or incorrect transaction ID: {}", transaction_id);
-            return;
+    // 已提交事务的提交时间戳，用于读取 "该事务提交后" 的状态
+    pub fn transaction_commit_ts(&self, transaction_id: u64) -> Result<u64, Error> {
+        let commit_ts = self.storage().transaction_commit_ts(transaction_id);
+        match commit_ts {
+            Some(commit_ts) => Ok(commit_ts),
+            None if self.is_active(transaction_id) => Err(Error::TxnNotCommitted(transaction_id)),
+            None => Err(Error::NotFound(Resource::Transaction(transaction_id))),
+        }
+    }
+
+    // 键的全部已提交版本 (包括删除标记)，从旧到新
+    pub fn history(&self, key: &String) -> Vec<Version> {
+        self.storage().history(key)
+    }
+
+    // 清除已过期的键：在一个内部事务中为它们写入删除标记，因此清除也会记录在日志和版本历史中
+    // 跳过活动事务写集中的键，避免与正在覆盖它们的事务冲突；返回清除的键数量
+    pub fn evict_expired(&self) -> Result<usize, Error> {
+        // 大多数时候没有过期的键，不必为此开始事务 (和写日志)；是否真正过期在事务开始后重新检查
+        if self.storage().expired_keys().is_empty() {
+            return Ok(0);
+        }
+        let transaction_id = self.begin_transaction()?;
+        let count = match self.evict_expired_in(transaction_id) {
+            Ok(count) => count,
+            Err(e) => {
+                let _ = self.rollback_transaction(transaction_id);
+                return Err(e);
+            }
+        };
+        if count == 0 {
+            self.rollback_transaction(transaction_id)?;
+            return Ok(0);
+        }
+        self.commit_transaction(transaction_id)?;
+        Ok(count)
+    }
+
+    // 在已经开始的事务中删除过期的键。只删除在事务快照中仍然过期的键：
+    // 事务开始后才被覆盖的键会被跳过，或者在提交时按先提交者胜出中止清除，不会丢失新写入的值
+    fn evict_expired_in(&self, transaction_id: u64) -> Result<usize, Error> {
+        let start_ts = self.transaction(transaction_id)?.start_ts;
+        let mut keys: BTreeSet<String> = {
+            let storage = self.storage();
+            storage.expired_keys().into_iter().filter(|key| storage.is_expired_at(key, start_ts)).collect()
+        };
+        let transactions: Vec<Arc<Transaction>> = read(&self.active_transactions).values().cloned().collect();
+        for transaction in transactions {
+            let state = lock(&transaction.state);
+            keys.retain(|key| !state.pending_writes.contains_key(key));
+        }
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        println!("TxnMgr: Evicting {} expired key(s)", keys.len());
+        let count = keys.len();
+        let transaction = self.writable_transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        for key in keys {
+            self.write_tombstone(transaction_id, &mut state, key)?;
+        }
+        Ok(count)
+    }
+
+    // 订阅之后的所有提交产生的变更
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.change_feed.subscribe()
+    }
+
+    // 订阅之后的变更，并取得范围内提交时间戳大于 since 的历史变更
+    // 持有提交锁完成这两步，两者之间不会有提交，因此不会遗漏或重复
+    pub fn subscribe_since(
+        &self,
+        range: &KeyRange,
+        since: Option<u64>,
+    ) -> Result<(Vec<ChangeEvent>, broadcast::Receiver<ChangeEvent>), Error> {
+        let _commit_lock = lock(&self.log_mgr);
+        let receiver = self.subscribe();
+        let replay = match since {
+            Some(since) => self.changes_since(range, since)?,
+            None => Vec::new(),
+        };
+        Ok((replay, receiver))
+    }
+
+    // 范围内提交时间戳大于 since_ts 的所有已提交变更，用于从某个序号恢复订阅
+    pub fn changes_since(&self, range: &KeyRange, since_ts: u64) -> Result<Vec<ChangeEvent>, Error> {
+        let storage = self.storage();
+        if since_ts < storage.oldest_readable_ts() {
+            return Err(Error::HistoryPruned(since_ts));
         }
-        println!("TxnMgr: Deleting key: {} for transaction ID: {} (not fully implemented)", key, transaction_id);
-        self.storage_engine.delete_data(key); // 调用 storage_engine 的删除
+        let mut events: Vec<ChangeEvent> = Vec::new();
+        for (key, version) in storage.versions_since(range, since_ts) {
+            let change = KeyChange { key, value: version.value };
+            match events.last_mut() {
+                Some(event) if event.commit_ts == version.commit_ts => event.changes.push(change),
+                _ => events.push(ChangeEvent { commit_ts: version.commit_ts, changes: vec![change] }),
+            }
+        }
+        Ok(events)
+    }
+
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        read(&self.retention_policy).clone()
     }
+
+    pub fn set_retention_policy(&self, policy: RetentionPolicy) {
+        println!("TxnMgr: Retention policy set to {:?}", policy);
+        *write(&self.retention_policy) = policy;
+    }
+
+    // 按保留策略回收历史版本；活动事务和正在提交的事务的快照时间戳会被固定，
+    // 不会丢失它们需要读取的版本，也不会丢失提交时检测冲突需要的删除标记
+    pub fn collect_garbage(&self) -> GcStats {
+        let policy = self.retention_policy();
+        let stats = {
+            // 持有活动事务的读锁，回收期间不会有新的快照出现，也不会有事务从活动事务移到正在提交的事务
+            let active = read(&self.active_transactions);
+            let mut pinned: Vec<u64> = active.values().map(|transaction| transaction.start_ts).collect();
+            pinned.extend(lock(&self.committing).values());
+            self.storage_mut().collect_garbage(&policy, &pinned)
+        };
+        println!(
+            "TxnMgr: Garbage collection removed {} version(s) and {} key(s), reclaimed {} bytes",
+            stats.versions_removed, stats.keys_removed, stats.bytes_reclaimed
+        );
+        let mut gc_metrics = lock(&self.gc_metrics);
+        gc_metrics.runs += 1;
+        gc_metrics.versions_removed += stats.versions_removed as u64;
+        gc_metrics.keys_removed += stats.keys_removed as u64;
+        gc_metrics.bytes_reclaimed += stats.bytes_reclaimed as u64;
+        gc_metrics.last_run = Some(stats);
+        stats
+    }
+
+    pub fn gc_metrics(&self) -> GcMetrics {
+        lock(&self.gc_metrics).clone()
+    }
+
+    // 在事务中删除键，提交前对其他事务不可见；返回删除前该键在事务中是否可见
+    // 与 StorageEngine::delete 一样，键不可见时不写入删除标记
+    pub fn delete(&self, transaction_id: u64, key: String) -> Result<bool, Error> {
+        let transaction = self.writable_transaction(transaction_id)?;
+        let mut state = self.transaction_state(transaction_id, &transaction)?;
+        let visible = match state.pending_writes.get(&key) {
+            Some(pending) => pending.is_some(),
+            None => {
+                let storage = self.storage();
+                storage.read_at(&key, transaction.read_ts(&storage)).is_some()
+            }
+        };
+        if !visible {
+            println!("TxnMgr: Key {} does not exist in transaction ID: {}, nothing to delete", key, transaction_id);
+            return Ok(false);
+        }
+        self.write_tombstone(transaction_id, &mut state, key)?;
+        Ok(true)
+    }
+
+    // 无条件地写入删除标记 (已过期的键读不到值，但仍然需要删除标记来清除)
+    fn write_tombstone(&self, transaction_id: u64, state: &mut TransactionState, key: String) -> Result<(), Error> {
+        println!("TxnMgr: Deleting key: {} for transaction ID: {}", key, transaction_id);
+        state.expirations.remove(&key);
+        state.pending_writes.insert(key, None);
+        Ok(())
+    }
+
+    // 创建命名快照：只记录当前最后一次提交的时间戳，之后可以通过版本历史读出当时的数据
+    pub fn create_snapshot(&self, snapshot_id: String) -> Result<SnapshotInfo, Error> {
+        let _commit_lock = lock(&self.log_mgr); // 数据文件只在持有提交锁时刷新
+        let commit_ts = {
+            let mut storage = self.storage_mut();
+            let commit_ts = storage.last_commit_ts();
+            if !storage.add_named_snapshot(snapshot_id.clone(), commit_ts) {
+                return Err(Error::AlreadyExists(Resource::Snapshot(snapshot_id)));
+            }
+            commit_ts
+        };
+        println!("TxnMgr: Created snapshot '{}' at ts {}", snapshot_id, commit_ts);
+        self.storage().flush_to_disk()?; // 数据文件中只有已提交的数据，随时可以刷新
+        Ok(SnapshotInfo { snapshot_id, commit_ts })
+    }
+
+    pub fn list_snapshots(&self) -> Vec<SnapshotInfo> {
+        self.storage()
+            .named_snapshots()
+            .iter()
+            .map(|(snapshot_id, commit_ts)| SnapshotInfo { snapshot_id: snapshot_id.clone(), commit_ts: *commit_ts })
+            .collect()
+    }
+
+    pub fn delete_snapshot(&self, snapshot_id: &str) -> Result<(), Error> {
+        let _commit_lock = lock(&self.log_mgr);
+        let removed = self.storage_mut().remove_named_snapshot(snapshot_id);
+        if removed.is_none() {
+            return Err(Error::NotFound(Resource::Snapshot(snapshot_id.to_string())));
+        }
+        println!("TxnMgr: Deleted snapshot '{}'", snapshot_id);
+        self.storage().flush_to_disk()?;
+        Ok(())
+    }
+
+    // 恢复到命名快照：在一个新事务中写回快照时的值 (并删除之后新增的键)，
+    // 因此恢复本身也是一次普通提交，会记录在日志和版本历史中，并且不影响其他快照
+    pub fn restore_snapshot(&self, snapshot_id: &str) -> Result<(), Error> {
+        let transaction_id = self.begin_transaction()?;
+        if let Err(e) = self.restore_snapshot_in(transaction_id, snapshot_id) {
+            let _ = self.rollback_transaction(transaction_id);
+            return Err(e);
+        }
+        self.commit_transaction(transaction_id)
+    }
+
+    // 与事务快照中的状态比较得出需要写回的键：事务开始后其他事务提交的修改在提交时按先提交者胜出中止恢复，
+    // 而不是被悄悄覆盖
+    fn restore_snapshot_in(&self, transaction_id: u64, snapshot_id: &str) -> Result<(), Error> {
+        let start_ts = self.transaction(transaction_id)?.start_ts;
+        let (snapshot_ts, writes) = {
+            let storage = self.storage();
+            let snapshot_ts = storage
+                .named_snapshot(snapshot_id)
+                .ok_or_else(|| Error::NotFound(Resource::Snapshot(snapshot_id.to_string())))?;
+            (snapshot_ts, storage.writes_to_restore(snapshot_ts, start_ts))
+        };
+        println!("TxnMgr: Restoring snapshot '{}' (ts {}), {} key(s) differ", snapshot_id, snapshot_ts, writes.len());
+        for (key, value) in writes {
+            match value {
+                Some(value) => self.execute_write_operation(transaction_id, key, value)?,
+                None => {
+                    self.delete(transaction_id, key)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_write_conflict(storage: &StorageEngine, start_ts: u64, state: &TransactionState) -> Option<String> {
+    state
+        .pending_writes
+        .keys()
+        .find(|key| storage.latest_commit_ts(key).is_some_and(|commit_ts| commit_ts > start_ts))
+        .cloned()
+}
+
+fn find_read_conflict(storage: &StorageEngine, start_ts: u64, state: &TransactionState) -> Option<String> {
+    let read_set = state.read_set.as_ref()?;
+    let modified = |key: &&String| storage.latest_commit_ts(key).is_some_and(|commit_ts| commit_ts > start_ts);
+    if let Some(key) = read_set.keys.iter().find(modified) {
+        return Some(key.clone());
+    }
+    read_set.ranges.iter().find_map(|range| storage.first_modified_since(range, start_ts))
 }
 
 #[cfg(test)]
@@ -104,17 +1009,833 @@ mod tests {
 
     #[test]
     fn test_txn_write_and_read() {
-        let mut tx_mgr = TxnMgr::new();
-        let tx_id = tx_mgr.begin_transaction();
+        let tx_mgr = TxnMgr::new();
+        let tx_id = tx_mgr.begin_transaction().unwrap();
         let key = "txn_test_key".to_string();
         let value = "txn_test_value".to_string();
 
-        tx_mgr.execute_write_operation(tx_id, key.clone(), value.clone());
-        tx_mgr.commit_transaction(tx_id); // 需要提交事务才能保证写入
+        tx_mgr.execute_write_operation(tx_id, key.clone(), value.clone()).unwrap();
+        tx_mgr.commit_transaction(tx_id).unwrap(); // 需要提交事务才能保证写入
 
         // 重新开始一个事务来读取 (或者允许在同一事务内读取，取决于设计)
-        let read_tx_id = tx_mgr.begin_transaction();
-        assert_eq!(tx_mgr.read_operation(read_tx_id, &key), Some(value));
-        tx_mgr.commit_transaction(read_tx_id);
+        let read_tx_id = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(tx_mgr.read_operation(read_tx_id, &key), Ok(Some(value)));
+        tx_mgr.commit_transaction(read_tx_id).unwrap();
+    }
+
+    #[test]
+    fn test_committed_data_survives_reopen() {
+        let dir = crate::storage::tests::temp_data_dir("txn_reopen");
+        {
+            let tx_mgr = TxnMgr::with_storage(StorageEngine::open(&dir).unwrap());
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(tx_id, "persisted".to_string(), "yes".to_string()).unwrap();
+            tx_mgr.commit_transaction(tx_id).unwrap();
+        }
+
+        let tx_mgr = TxnMgr::with_storage(StorageEngine::open(&dir).unwrap());
+        let read_tx_id = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(tx_mgr.read_operation(read_tx_id, &"persisted".to_string()), Ok(Some("yes".to_string())));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wal_recovers_committed_transactions_after_crash() {
+        let dir = crate::storage::tests::temp_data_dir("txn_wal_crash");
+        {
+            let tx_mgr = TxnMgr::open(&dir).unwrap();
+            let committed = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(committed, "a".to_string(), "1".to_string()).unwrap();
+            tx_mgr.commit_transaction(committed).unwrap();
+
+            let uncommitted = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(uncommitted, "b".to_string(), "2".to_string()).unwrap();
+        } // 没有检查点也没有提交就丢弃，模拟进程崩溃
+
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(tx_mgr.read_operation(tx_id, &"a".to_string()), Ok(Some("1".to_string())));
+        assert_eq!(tx_mgr.read_operation(tx_id, &"b".to_string()), Ok(None));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_during_an_active_transaction_keeps_its_writes() {
+        let dir = crate::storage::tests::temp_data_dir("txn_checkpoint_active");
+        {
+            let tx_mgr = TxnMgr::open(&dir).unwrap();
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(tx_id, "a".to_string(), "1".to_string()).unwrap();
+            // 活动事务不会阻止检查点：它的写入在提交时才写入日志
+            tx_mgr.checkpoint().unwrap();
+            assert_eq!(lock(&tx_mgr.log_mgr).as_ref().unwrap().len(), Ok(0));
+            tx_mgr.commit_transaction(tx_id).unwrap();
+        } // 提交后没有检查点就丢弃，模拟进程崩溃
+
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(tx_mgr.read_operation(tx_id, &"a".to_string()), Ok(Some("1".to_string())));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_overlapping_transactions_keep_their_own_undo_data() {
+        let tx_mgr = TxnMgr::new();
+        let first = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(first, "first_key".to_string(), "1".to_string()).unwrap();
+
+        // 第二个事务开始后，第一个事务仍然活动，回滚时仍然能撤销自己的写入
+        let second = tx_mgr.begin_transaction().unwrap();
+        assert_ne!(first, second);
+        tx_mgr.execute_write_operation(second, "second_key".to_string(), "2".to_string()).unwrap();
+        tx_mgr.rollback_transaction(first).unwrap();
+        tx_mgr.commit_transaction(second).unwrap();
+
+        assert_eq!(tx_mgr.read_committed(&"first_key".to_string()), None);
+        assert_eq!(tx_mgr.read_committed(&"second_key".to_string()), Some("2".to_string()));
+    }
+
+    #[test]
+    fn test_transaction_ids_are_never_reused() {
+        let tx_mgr = TxnMgr::new();
+        let first = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.commit_transaction(first).unwrap();
+        let second = tx_mgr.begin_transaction().unwrap();
+        assert!(second > first);
+    }
+
+    #[test]
+    fn test_operations_on_unknown_or_finished_transactions_fail() {
+        let tx_mgr = TxnMgr::new();
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.commit_transaction(tx_id).unwrap();
+
+        assert_eq!(tx_mgr.commit_transaction(tx_id), Err(Error::TxnNotActive(tx_id)));
+        assert_eq!(
+            tx_mgr.execute_write_operation(tx_id, "k".to_string(), "v".to_string()),
+            Err(Error::TxnNotActive(tx_id))
+        );
+        assert_eq!(tx_mgr.rollback_transaction(42), Err(Error::NotFound(Resource::Transaction(42))));
+        assert_eq!(tx_mgr.delete(42, "k".to_string()), Err(Error::NotFound(Resource::Transaction(42))));
+        assert_eq!(tx_mgr.read_operation(tx_id, &"k".to_string()), Err(Error::TxnNotActive(tx_id)));
+    }
+
+    #[test]
+    fn test_snapshot_isolation_reads() {
+        let tx_mgr = TxnMgr::new();
+        let key = "si_key".to_string();
+        let setup = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(setup, key.clone(), "old".to_string()).unwrap();
+        tx_mgr.commit_transaction(setup).unwrap();
+
+        let reader = tx_mgr.begin_transaction().unwrap();
+        let writer = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(writer, key.clone(), "new".to_string()).unwrap();
+
+        // 写入者能看到自己的写入，其他事务和非事务读取看不到未提交的写入
+        assert_eq!(tx_mgr.read_operation(writer, &key), Ok(Some("new".to_string())));
+        assert_eq!(tx_mgr.read_operation(reader, &key), Ok(Some("old".to_string())));
+        assert_eq!(tx_mgr.read_committed(&key), Some("old".to_string()));
+
+        // 提交后，已经开始的读事务仍然读取自己的快照
+        tx_mgr.commit_transaction(writer).unwrap();
+        assert_eq!(tx_mgr.read_operation(reader, &key), Ok(Some("old".to_string())));
+        assert_eq!(tx_mgr.read_committed(&key), Some("new".to_string()));
+
+        let later = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(tx_mgr.read_operation(later, &key), Ok(Some("new".to_string())));
+    }
+
+    #[test]
+    fn test_uncommitted_delete_is_invisible_to_other_transactions() {
+        let tx_mgr = TxnMgr::new();
+        let key = "si_delete".to_string();
+        let setup = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(setup, key.clone(), "kept".to_string()).unwrap();
+        tx_mgr.commit_transaction(setup).unwrap();
+
+        let deleter = tx_mgr.begin_transaction().unwrap();
+        let reader = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.delete(deleter, key.clone()).unwrap();
+        assert_eq!(tx_mgr.read_operation(deleter, &key), Ok(None));
+        assert_eq!(tx_mgr.read_operation(reader, &key), Ok(Some("kept".to_string())));
+
+        tx_mgr.commit_transaction(deleter).unwrap();
+        assert_eq!(tx_mgr.read_operation(reader, &key), Ok(Some("kept".to_string())));
+        assert_eq!(tx_mgr.read_committed(&key), None);
+    }
+
+    #[test]
+    fn test_deleting_a_missing_key_writes_nothing() {
+        let dir = crate::storage::tests::temp_data_dir("txn_delete_missing");
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        let key = "missing".to_string();
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        let wal_len = || lock(&tx_mgr.log_mgr).as_ref().unwrap().len().unwrap();
+        let before = wal_len();
+        assert_eq!(tx_mgr.delete(tx_id, key.clone()), Ok(false));
+        assert_eq!(wal_len(), before);
+
+        tx_mgr.execute_write_operation(tx_id, key.clone(), "v".to_string()).unwrap();
+        assert_eq!(tx_mgr.delete(tx_id, key.clone()), Ok(true));
+        assert_eq!(tx_mgr.delete(tx_id, key.clone()), Ok(false));
+        tx_mgr.commit_transaction(tx_id).unwrap();
+        assert_eq!(tx_mgr.history(&key).len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rolled_back_delete_keeps_the_key() {
+        let dir = crate::storage::tests::temp_data_dir("txn_rollback_delete");
+        let key = "undeleted".to_string();
+        {
+            let tx_mgr = TxnMgr::open(&dir).unwrap();
+            let setup = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(setup, key.clone(), "kept".to_string()).unwrap();
+            tx_mgr.commit_transaction(setup).unwrap();
+
+            let deleter = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.delete(deleter, key.clone()).unwrap();
+            tx_mgr.rollback_transaction(deleter).unwrap();
+            assert_eq!(tx_mgr.read_committed(&key), Some("kept".to_string()));
+            assert_eq!(tx_mgr.history(&key).len(), 1); // 回滚的删除不会留下删除标记
+        }
+
+        // 被回滚的删除不会写入日志，恢复后键仍然存在
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        assert_eq!(tx_mgr.read_committed(&key), Some("kept".to_string()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_first_committer_wins_on_write_write_conflict() {
+        let tx_mgr = TxnMgr::new();
+        let key = "counter".to_string();
+        let first = tx_mgr.begin_transaction().unwrap();
+        let second = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(first, key.clone(), "from_first".to_string()).unwrap();
+        tx_mgr.execute_write_operation(second, key.clone(), "from_second".to_string()).unwrap();
+
+        tx_mgr.commit_transaction(first).unwrap();
+        assert_eq!(
+            tx_mgr.commit_transaction(second),
+            Err(Error::Conflict { transaction_id: second, key: key.clone() })
+        );
+
+        // 冲突的事务已被中止，它的写入没有生效
+        assert!(!tx_mgr.is_active(second));
+        assert_eq!(tx_mgr.read_committed(&key), Some("from_first".to_string()));
+
+        // 重试的事务从新的快照开始，可以成功提交
+        let retry = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(retry, key.clone(), "from_second".to_string()).unwrap();
+        tx_mgr.commit_transaction(retry).unwrap();
+        assert_eq!(tx_mgr.read_committed(&key), Some("from_second".to_string()));
+    }
+
+    #[test]
+    fn test_disjoint_writes_do_not_conflict() {
+        let tx_mgr = TxnMgr::new();
+        let first = tx_mgr.begin_transaction().unwrap();
+        let second = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(first, "a".to_string(), "1".to_string()).unwrap();
+        tx_mgr.delete(second, "b".to_string()).unwrap();
+        tx_mgr.commit_transaction(first).unwrap();
+        tx_mgr.commit_transaction(second).unwrap();
+    }
+
+    #[test]
+    fn test_persistence_failures_are_returned() {
+        let dir = crate::storage::tests::temp_data_dir("txn_persist_failure");
+        // 没有预写日志时提交直接刷新数据文件
+        let tx_mgr = TxnMgr::with_storage(StorageEngine::open(&dir).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(tx_id, "k".to_string(), "v".to_string()).unwrap();
+        assert!(matches!(tx_mgr.commit_transaction(tx_id), Err(Error::Io { .. })));
+        assert!(matches!(tx_mgr.create_snapshot("snap".to_string()), Err(Error::Io { .. })));
+    }
+
+    #[test]
+    fn test_replayed_versions_keep_their_commit_time() {
+        let dir = crate::storage::tests::temp_data_dir("txn_committed_at");
+        let key = "aged".to_string();
+        let history = {
+            let tx_mgr = TxnMgr::open(&dir).unwrap();
+            for value in ["v1", "v2"] {
+                let tx_id = tx_mgr.begin_transaction().unwrap();
+                tx_mgr.execute_write_operation(tx_id, key.clone(), value.to_string()).unwrap();
+                tx_mgr.commit_transaction(tx_id).unwrap();
+            }
+            tx_mgr.history(&key)
+        };
+        std::thread::sleep(Duration::from_millis(5));
+
+        // 版本只在日志中，重启时重放；按时间的保留策略依赖原来的提交时间
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        assert_eq!(tx_mgr.history(&key), history);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_named_snapshot_restore_and_persistence() {
+        let dir = crate::storage::tests::temp_data_dir("txn_named_snapshots");
+        {
+            let tx_mgr = TxnMgr::open(&dir).unwrap();
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(tx_id, "k".to_string(), "snap1".to_string()).unwrap();
+            tx_mgr.commit_transaction(tx_id).unwrap();
+            tx_mgr.create_snapshot("snap1".to_string()).unwrap();
+            assert_eq!(
+                tx_mgr.create_snapshot("snap1".to_string()),
+                Err(Error::AlreadyExists(Resource::Snapshot("snap1".to_string())))
+            );
+
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(tx_id, "k".to_string(), "snap2".to_string()).unwrap();
+            tx_mgr.execute_write_operation(tx_id, "extra".to_string(), "x".to_string()).unwrap();
+            tx_mgr.commit_transaction(tx_id).unwrap();
+            tx_mgr.create_snapshot("snap2".to_string()).unwrap();
+        }
+
+        // 重启后快照仍然存在，并且可以在两个快照之间来回恢复
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        let ids: Vec<String> = tx_mgr.list_snapshots().into_iter().map(|info| info.snapshot_id).collect();
+        assert_eq!(ids, vec!["snap1".to_string(), "snap2".to_string()]);
+
+        tx_mgr.restore_snapshot("snap1").unwrap();
+        assert_eq!(tx_mgr.read_committed(&"k".to_string()), Some("snap1".to_string()));
+        assert_eq!(tx_mgr.read_committed(&"extra".to_string()), None);
+
+        tx_mgr.restore_snapshot("snap2").unwrap();
+        assert_eq!(tx_mgr.read_committed(&"k".to_string()), Some("snap2".to_string()));
+        assert_eq!(tx_mgr.read_committed(&"extra".to_string()), Some("x".to_string()));
+
+        tx_mgr.delete_snapshot("snap1").unwrap();
+        assert_eq!(tx_mgr.restore_snapshot("snap1"), Err(Error::NotFound(Resource::Snapshot("snap1".to_string()))));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_restore_does_not_overwrite_a_concurrent_commit() {
+        let tx_mgr = TxnMgr::new();
+        let write = |value: &str| {
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(tx_id, "k".to_string(), value.to_string()).unwrap();
+            tx_mgr.commit_transaction(tx_id).unwrap();
+        };
+        write("v1");
+        tx_mgr.create_snapshot("snap".to_string()).unwrap();
+        write("v2");
+
+        // 恢复开始之后提交的写入先提交，恢复被中止
+        let restore = tx_mgr.begin_transaction().unwrap();
+        write("v3");
+        tx_mgr.restore_snapshot_in(restore, "snap").unwrap();
+        assert!(matches!(tx_mgr.commit_transaction(restore), Err(Error::Conflict { .. })));
+        assert_eq!(tx_mgr.read_committed(&"k".to_string()), Some("v3".to_string()));
+
+        tx_mgr.restore_snapshot("snap").unwrap();
+        assert_eq!(tx_mgr.read_committed(&"k".to_string()), Some("v1".to_string()));
+    }
+
+    #[test]
+    fn test_point_in_time_reads() {
+        let tx_mgr = TxnMgr::new();
+        let key = "pit_key".to_string();
+        let tx1 = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(tx1, key.clone(), "v1".to_string()).unwrap();
+        tx_mgr.commit_transaction(tx1).unwrap();
+        let tx2 = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.delete(tx2, key.clone()).unwrap();
+        tx_mgr.commit_transaction(tx2).unwrap();
+        let open_tx = tx_mgr.begin_transaction().unwrap();
+
+        let ts1 = tx_mgr.transaction_commit_ts(tx1).unwrap();
+        let ts2 = tx_mgr.transaction_commit_ts(tx2).unwrap();
+        assert_eq!(tx_mgr.read_as_of(&key, ts1), Ok(Some("v1".to_string())));
+        assert_eq!(tx_mgr.read_as_of(&key, ts2), Ok(None));
+        assert_eq!(tx_mgr.read_as_of(&key, ts2 + 1), Err(Error::FutureTimestamp(ts2 + 1)));
+        assert_eq!(tx_mgr.transaction_commit_ts(open_tx), Err(Error::TxnNotCommitted(open_tx)));
+        assert_eq!(tx_mgr.transaction_commit_ts(999), Err(Error::NotFound(Resource::Transaction(999))));
+        assert_eq!(tx_mgr.history(&key).len(), 2);
+    }
+
+    #[test]
+    fn test_garbage_collection_keeps_versions_of_active_transactions() {
+        let tx_mgr = TxnMgr::new();
+        tx_mgr.set_retention_policy(RetentionPolicy { keep_last: 1, keep_newer_than: None });
+        let key = "gc_key".to_string();
+        for value in ["v1", "v2"] {
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(tx_id, key.clone(), value.to_string()).unwrap();
+            tx_mgr.commit_transaction(tx_id).unwrap();
+        }
+        let reader = tx_mgr.begin_transaction().unwrap(); // 快照中 key = v2
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(tx_id, key.clone(), "v3".to_string()).unwrap();
+        tx_mgr.commit_transaction(tx_id).unwrap();
+
+        assert_eq!(tx_mgr.collect_garbage().versions_removed, 1); // 只有 v1 被回收
+        assert_eq!(tx_mgr.read_operation(reader, &key), Ok(Some("v2".to_string())));
+        assert_eq!(tx_mgr.read_as_of(&key, 1), Err(Error::HistoryPruned(1)));
+        assert_eq!(tx_mgr.read_as_of(&key, 2), Ok(Some("v2".to_string())));
+
+        tx_mgr.commit_transaction(reader).unwrap();
+        tx_mgr.collect_garbage();
+        assert_eq!(tx_mgr.history(&key).len(), 1);
+        assert_eq!(tx_mgr.gc_metrics().runs, 2);
+        assert_eq!(tx_mgr.gc_metrics().versions_removed, 2);
+    }
+
+    #[test]
+    fn test_garbage_collection_during_a_commit_keeps_the_conflicting_delete() {
+        let tx_mgr = TxnMgr::new();
+        tx_mgr.set_retention_policy(RetentionPolicy { keep_last: 1, keep_newer_than: None });
+        let key = "gc_key".to_string();
+        let writer = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(writer, key.clone(), "v1".to_string()).unwrap();
+        tx_mgr.commit_transaction(writer).unwrap();
+
+        let stale = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(stale, key.clone(), "v2".to_string()).unwrap();
+        let deleter = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(tx_mgr.delete(deleter, key.clone()), Ok(true));
+        tx_mgr.commit_transaction(deleter).unwrap();
+
+        // 回收发生在 stale 已从活动事务中移除、还没有验证时：只剩删除标记的键不能被移除
+        let transaction = tx_mgr.start_commit(stale).unwrap();
+        assert_eq!(tx_mgr.collect_garbage().keys_removed, 0);
+        let result = tx_mgr.commit_removed(stale, &transaction);
+        lock(&tx_mgr.committing).remove(&stale);
+        assert_eq!(result, Err(Error::Conflict { transaction_id: stale, key: key.clone() }));
+
+        assert_eq!(tx_mgr.collect_garbage().keys_removed, 1);
+        assert!(tx_mgr.history(&key).is_empty());
+    }
+
+    #[test]
+    fn test_scan_merges_pending_writes_with_snapshot() {
+        let tx_mgr = TxnMgr::new();
+        let setup = tx_mgr.begin_transaction().unwrap();
+        for key in ["a", "b", "c", "d"] {
+            tx_mgr.execute_write_operation(setup, key.to_string(), "committed".to_string()).unwrap();
+        }
+        tx_mgr.commit_transaction(setup).unwrap();
+
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(tx_id, "b".to_string(), "mine".to_string()).unwrap();
+        tx_mgr.execute_write_operation(tx_id, "bb".to_string(), "new".to_string()).unwrap();
+        tx_mgr.delete(tx_id, "c".to_string()).unwrap();
+
+        // 事务开始后其他事务提交的键对本事务不可见
+        let other = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(other, "ab".to_string(), "later".to_string()).unwrap();
+        tx_mgr.commit_transaction(other).unwrap();
+
+        let all = KeyRange::default();
+        assert_eq!(
+            tx_mgr.scan(tx_id, &all, 10).unwrap(),
+            vec![
+                ("a".to_string(), "committed".to_string()),
+                ("b".to_string(), "mine".to_string()),
+                ("bb".to_string(), "new".to_string()),
+                ("d".to_string(), "committed".to_string()),
+            ]
+        );
+        assert_eq!(tx_mgr.scan(tx_id, &KeyRange::prefix("b"), 1).unwrap().len(), 1);
+        let latest: Vec<String> = tx_mgr.scan_committed(&all, 10).into_iter().map(|(key, _)| key).collect();
+        assert_eq!(latest, vec!["a", "ab", "b", "c", "d"]);
+        assert_eq!(tx_mgr.scan(999, &all, 10), Err(Error::NotFound(Resource::Transaction(999))));
+    }
+
+    #[test]
+    fn test_conditional_writes_and_increment() {
+        let tx_mgr = TxnMgr::new();
+        let key = "lease".to_string();
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.compare_and_swap(tx_id, key.clone(), None, Some("node-a".to_string())).unwrap();
+        assert_eq!(
+            tx_mgr.compare_and_swap(tx_id, key.clone(), None, Some("node-b".to_string())),
+            Err(Error::PreconditionFailed { key: key.clone(), actual: Some("node-a".to_string()) })
+        );
+        tx_mgr.compare_and_swap(tx_id, key.clone(), Some("node-a"), None).unwrap();
+        assert_eq!(tx_mgr.read_operation(tx_id, &key), Ok(None));
+
+        assert_eq!(tx_mgr.increment(tx_id, "counter".to_string(), 5), Ok(5));
+        assert_eq!(tx_mgr.increment(tx_id, "counter".to_string(), -2), Ok(3));
+        tx_mgr.execute_write_operation(tx_id, "text".to_string(), "abc".to_string()).unwrap();
+        assert!(matches!(tx_mgr.increment(tx_id, "text".to_string(), 1), Err(Error::TypeMismatch { .. })));
+        tx_mgr.commit_transaction(tx_id).unwrap();
+        assert_eq!(tx_mgr.read_committed(&"counter".to_string()), Some("3".to_string()));
+    }
+
+    #[test]
+    fn test_concurrent_increments_do_not_lose_updates() {
+        let tx_mgr = TxnMgr::new();
+        let key = "hits".to_string();
+        let first = tx_mgr.begin_transaction().unwrap();
+        let second = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.increment(first, key.clone(), 1).unwrap();
+        tx_mgr.increment(second, key.clone(), 1).unwrap();
+        tx_mgr.commit_transaction(first).unwrap();
+        // 两个事务都基于快照中的 0 计算，后提交的事务必须冲突而不是覆盖成 1
+        assert!(matches!(tx_mgr.commit_transaction(second), Err(Error::Conflict { .. })));
+        assert_eq!(tx_mgr.read_committed(&key), Some("1".to_string()));
+    }
+
+    #[test]
+    fn test_increments_from_many_threads_do_not_lose_updates() {
+        let tx_mgr = TxnMgr::new();
+        let key = "hits".to_string();
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..25 {
+                        // 冲突时重试，直到基于最新的值提交成功
+                        loop {
+                            let tx_id = tx_mgr.begin_transaction().unwrap();
+                            tx_mgr.increment(tx_id, key.clone(), 1).unwrap();
+                            match tx_mgr.commit_transaction(tx_id) {
+                                Ok(()) => break,
+                                Err(Error::Conflict { .. }) => continue,
+                                Err(e) => panic!("unexpected error: {}", e),
+                            }
+                        }
+                        assert!(tx_mgr.read_committed(&key).is_some());
+                    }
+                });
+            }
+        });
+        assert_eq!(tx_mgr.read_committed(&key), Some("200".to_string()));
+    }
+
+    #[test]
+    fn test_ttl_writes_expire_and_are_evicted() {
+        let dir = crate::storage::tests::temp_data_dir("txn_ttl");
+        {
+            let tx_mgr = TxnMgr::open(&dir).unwrap();
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr
+                .execute_write_operation_with_ttl(tx_id, "session".to_string(), "token".to_string(), Some(Duration::from_secs(3600)))
+                .unwrap();
+            tx_mgr.execute_write_operation_with_ttl(tx_id, "stale".to_string(), "x".to_string(), Some(Duration::ZERO)).unwrap();
+            tx_mgr.commit_transaction(tx_id).unwrap();
+            assert_eq!(tx_mgr.read_committed(&"stale".to_string()), None);
+        }
+
+        // TTL 通过日志恢复后仍然有效
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        assert_eq!(tx_mgr.read_committed(&"session".to_string()), Some("token".to_string()));
+        assert_eq!(tx_mgr.read_committed(&"stale".to_string()), None);
+        assert_eq!(tx_mgr.evict_expired(), Ok(1));
+        assert_eq!(tx_mgr.history(&"stale".to_string()).last().unwrap().value, None);
+        assert_eq!(tx_mgr.evict_expired(), Ok(0));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_eviction_never_deletes_a_value_written_after_it_started() {
+        let tx_mgr = TxnMgr::new();
+        let write = |key: &str, value: &str, ttl: Option<Duration>| {
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation_with_ttl(tx_id, key.to_string(), value.to_string(), ttl).unwrap();
+            tx_mgr.commit_transaction(tx_id).unwrap();
+        };
+        write("stale", "x", Some(Duration::ZERO));
+        write("session", "old", Some(Duration::ZERO));
+
+        // 清除开始后、选出过期键之前，session 被重新写入：它不再过期，清除跳过它
+        let eviction = tx_mgr.begin_transaction().unwrap();
+        write("session", "fresh", None);
+        assert_eq!(tx_mgr.evict_expired_in(eviction), Ok(1));
+        tx_mgr.commit_transaction(eviction).unwrap();
+        assert_eq!(tx_mgr.read_committed(&"session".to_string()), Some("fresh".to_string()));
+        assert_eq!(tx_mgr.history(&"stale".to_string()).last().unwrap().value, None);
+
+        // 选出过期键之后才被重新写入：先提交的写入胜出，清除被中止
+        write("session", "old", Some(Duration::ZERO));
+        let eviction = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(tx_mgr.evict_expired_in(eviction), Ok(1));
+        write("session", "fresh", None);
+        assert!(matches!(tx_mgr.commit_transaction(eviction), Err(Error::Conflict { .. })));
+        assert_eq!(tx_mgr.read_committed(&"session".to_string()), Some("fresh".to_string()));
+        assert_eq!(tx_mgr.evict_expired(), Ok(0));
+    }
+
+    #[test]
+    fn test_change_feed_only_publishes_committed_writes() {
+        let tx_mgr = TxnMgr::new();
+        let mut feed = tx_mgr.subscribe();
+        let rolled_back = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(rolled_back, "config/a".to_string(), "never".to_string()).unwrap();
+        tx_mgr.rollback_transaction(rolled_back).unwrap();
+
+        let tx_id = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(tx_id, "config/a".to_string(), "1".to_string()).unwrap();
+        // 同一个事务中先写后删，提交的是删除标记
+        tx_mgr.execute_write_operation(tx_id, "config/b".to_string(), "tmp".to_string()).unwrap();
+        assert_eq!(tx_mgr.delete(tx_id, "config/b".to_string()), Ok(true));
+        assert!(feed.try_recv().is_err()); // 提交之前没有任何事件
+        tx_mgr.commit_transaction(tx_id).unwrap();
+
+        let event = feed.try_recv().unwrap();
+        assert_eq!(
+            event,
+            ChangeEvent {
+                commit_ts: 1,
+                changes: vec![
+                    KeyChange { key: "config/a".to_string(), value: Some("1".to_string()) },
+                    KeyChange { key: "config/b".to_string(), value: None },
+                ],
+            }
+        );
+        assert!(feed.try_recv().is_err());
+        assert_eq!(event.filter(&KeyRange::single("config/b")).unwrap().changes.len(), 1);
+        assert_eq!(event.filter(&KeyRange::prefix("other/")), None);
+
+        // 从序号恢复时通过版本历史重放
+        assert_eq!(tx_mgr.changes_since(&KeyRange::prefix("config/"), 0), Ok(vec![event]));
+        assert_eq!(tx_mgr.changes_since(&KeyRange::single("config/a"), 1), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_expired_transactions_are_rolled_back() {
+        let tx_mgr = TxnMgr::new();
+        tx_mgr.set_default_timeout(Some(Duration::ZERO));
+        let abandoned = tx_mgr.begin_transaction().unwrap();
+        let reaped = tx_mgr.begin_transaction().unwrap();
+        let kept = tx_mgr.begin_transaction_with_timeout(None).unwrap();
+        tx_mgr.execute_write_operation(kept, "kept".to_string(), "yes".to_string()).unwrap();
+
+        // 未被后台任务回收的超时事务在下一次操作时被回滚
+        assert_eq!(tx_mgr.read_operation(abandoned, &"kept".to_string()), Err(Error::TxnTimedOut(abandoned)));
+        assert_eq!(
+            tx_mgr.execute_write_operation(abandoned, "lost".to_string(), "no".to_string()),
+            Err(Error::TxnTimedOut(abandoned))
+        );
+        // 回收之前已经拿到事务的请求之后的操作也会失败
+        let held = read(&tx_mgr.active_transactions).get(&reaped).cloned().unwrap();
+        assert_eq!(tx_mgr.reap_expired_transactions(), vec![reaped]);
+        assert_eq!(tx_mgr.transaction_state(reaped, &held).err(), Some(Error::TxnTimedOut(reaped)));
+        assert_eq!(tx_mgr.commit_transaction(reaped), Err(Error::TxnTimedOut(reaped)));
+        assert!(!tx_mgr.is_active(abandoned) && !tx_mgr.is_active(reaped));
+
+        tx_mgr.commit_transaction(kept).unwrap();
+        assert_eq!(tx_mgr.read_committed(&"kept".to_string()), Some("yes".to_string()));
+    }
+
+    #[test]
+    fn test_savepoints_roll_back_partial_work() {
+        let dir = crate::storage::tests::temp_data_dir("txn_savepoints");
+        {
+            let tx_mgr = TxnMgr::open(&dir).unwrap();
+            let tx_id = tx_mgr.begin_transaction().unwrap();
+            tx_mgr.execute_write_operation(tx_id, "step1".to_string(), "done".to_string()).unwrap();
+            tx_mgr.create_savepoint(tx_id, "after_step1".to_string()).unwrap();
+            tx_mgr.execute_write_operation(tx_id, "step1".to_string(), "overwritten".to_string()).unwrap();
+            tx_mgr.execute_write_operation(tx_id, "step2".to_string(), "bad".to_string()).unwrap();
+            tx_mgr.create_savepoint(tx_id, "inner".to_string()).unwrap();
+
+            tx_mgr.rollback_to_savepoint(tx_id, "after_step1").unwrap();
+            assert_eq!(tx_mgr.read_operation(tx_id, &"step1".to_string()), Ok(Some("done".to_string())));
+            assert_eq!(tx_mgr.read_operation(tx_id, &"step2".to_string()), Ok(None));
+            assert_eq!(tx_mgr.savepoints(tx_id), Ok(vec!["after_step1".to_string()]));
+            assert_eq!(
+                tx_mgr.rollback_to_savepoint(tx_id, "inner"),
+                Err(Error::NotFound(Resource::Savepoint { transaction_id: tx_id, name: "inner".to_string() }))
+            );
+
+            tx_mgr.execute_write_operation(tx_id, "step2".to_string(), "good".to_string()).unwrap();
+            tx_mgr.release_savepoint(tx_id, "after_step1").unwrap();
+            assert_eq!(tx_mgr.savepoints(tx_id), Ok(vec![]));
+            tx_mgr.commit_transaction(tx_id).unwrap();
+        }
+
+        // 日志重放得到与提交时相同的结果
+        let tx_mgr = TxnMgr::open(&dir).unwrap();
+        assert_eq!(tx_mgr.read_committed(&"step1".to_string()), Some("done".to_string()));
+        assert_eq!(tx_mgr.read_committed(&"step2".to_string()), Some("good".to_string()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_only_transactions_reject_writes() {
+        let tx_mgr = TxnMgr::new();
+        let setup = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(setup, "k".to_string(), "v".to_string()).unwrap();
+        tx_mgr.commit_transaction(setup).unwrap();
+        let last_commit_ts = tx_mgr.storage().last_commit_ts();
+
+        let options = BeginOptions { mode: TransactionMode::ReadOnly, ..tx_mgr.default_begin_options() };
+        let tx_id = tx_mgr.begin_transaction_with_options(options).unwrap();
+        assert_eq!(
+            tx_mgr.execute_write_operation(tx_id, "k".to_string(), "x".to_string()),
+            Err(Error::TxnReadOnly(tx_id))
+        );
+        assert_eq!(tx_mgr.delete(tx_id, "k".to_string()), Err(Error::TxnReadOnly(tx_id)));
+        assert_eq!(tx_mgr.read_operation(tx_id, &"k".to_string()), Ok(Some("v".to_string())));
+
+        // 没有写入的提交不会分配新的提交时间戳，也不会记录它的提交
+        tx_mgr.commit_transaction(tx_id).unwrap();
+        assert_eq!(tx_mgr.storage().last_commit_ts(), last_commit_ts);
+        assert_eq!(tx_mgr.storage().transaction_commit_ts(tx_id), None);
+        assert_eq!(tx_mgr.commit_transaction(tx_id), Err(Error::TxnNotActive(tx_id)));
+    }
+
+    #[test]
+    fn test_isolation_level_controls_visibility_of_later_commits() {
+        let tx_mgr = TxnMgr::new();
+        let read_committed = tx_mgr.begin_transaction_with_options(BeginOptions {
+            isolation: IsolationLevel::ReadCommitted,
+            ..tx_mgr.default_begin_options()
+        }).unwrap();
+        let snapshot = tx_mgr.begin_transaction().unwrap();
+
+        let writer = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(writer, "k".to_string(), "new".to_string()).unwrap();
+        tx_mgr.commit_transaction(writer).unwrap();
+
+        let key = "k".to_string();
+        assert_eq!(tx_mgr.read_operation(read_committed, &key), Ok(Some("new".to_string())));
+        assert_eq!(tx_mgr.read_operation(snapshot, &key), Ok(None));
+        let all = KeyRange::default();
+        assert_eq!(tx_mgr.scan(read_committed, &all, 10).unwrap().len(), 1);
+        assert!(tx_mgr.scan(snapshot, &all, 10).unwrap().is_empty());
+    }
+
+    fn begin_serializable(tx_mgr: &TxnMgr) -> u64 {
+        let options = BeginOptions { isolation: IsolationLevel::Serializable, ..tx_mgr.default_begin_options() };
+        tx_mgr.begin_transaction_with_options(options).unwrap()
+    }
+
+    // 值班表：至少要有一个医生在值班。两个事务各自看到两人都在值班，于是各自让不同的人下班
+    fn run_on_call_write_skew(tx_mgr: &TxnMgr, first: u64, second: u64) -> (Result<(), Error>, Result<(), Error>) {
+        for (tx_id, leaving) in [(first, "on_call/alice"), (second, "on_call/bob")] {
+            let on_call = tx_mgr.scan(tx_id, &KeyRange::prefix("on_call/"), 10).unwrap();
+            assert_eq!(on_call.iter().filter(|(_, value)| value == "yes").count(), 2);
+            tx_mgr.execute_write_operation(tx_id, leaving.to_string(), "no".to_string()).unwrap();
+        }
+        (tx_mgr.commit_transaction(first), tx_mgr.commit_transaction(second))
+    }
+
+    fn on_call_roster() -> TxnMgr {
+        let tx_mgr = TxnMgr::new();
+        let setup = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(setup, "on_call/alice".to_string(), "yes".to_string()).unwrap();
+        tx_mgr.execute_write_operation(setup, "on_call/bob".to_string(), "yes".to_string()).unwrap();
+        tx_mgr.commit_transaction(setup).unwrap();
+        tx_mgr
+    }
+
+    #[test]
+    fn test_snapshot_isolation_allows_write_skew() {
+        let tx_mgr = on_call_roster();
+        let first = tx_mgr.begin_transaction().unwrap();
+        let second = tx_mgr.begin_transaction().unwrap();
+        assert_eq!(run_on_call_write_skew(&tx_mgr, first, second), (Ok(()), Ok(())));
+        let on_call = tx_mgr.scan_committed(&KeyRange::prefix("on_call/"), 10);
+        assert!(on_call.iter().all(|(_, value)| value == "no"));
+    }
+
+    #[test]
+    fn test_serializable_prevents_write_skew() {
+        let tx_mgr = on_call_roster();
+        let first = begin_serializable(&tx_mgr);
+        let second = begin_serializable(&tx_mgr);
+        let (first_result, second_result) = run_on_call_write_skew(&tx_mgr, first, second);
+        assert_eq!(first_result, Ok(()));
+        assert_eq!(
+            second_result,
+            Err(Error::SerializationFailure { transaction_id: second, key: "on_call/alice".to_string() })
+        );
+        assert_eq!(tx_mgr.read_committed(&"on_call/bob".to_string()), Some("yes".to_string()));
+        assert!(!tx_mgr.is_active(second));
+    }
+
+    #[test]
+    fn test_serializable_prevents_write_skew_on_point_reads() {
+        // 两个账户的余额之和不能为负：每个事务读取两个余额，然后从不同的账户扣款
+        let tx_mgr = TxnMgr::new();
+        let setup = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(setup, "checking".to_string(), "70".to_string()).unwrap();
+        tx_mgr.execute_write_operation(setup, "savings".to_string(), "80".to_string()).unwrap();
+        tx_mgr.commit_transaction(setup).unwrap();
+
+        let first = begin_serializable(&tx_mgr);
+        let second = begin_serializable(&tx_mgr);
+        for (tx_id, account) in [(first, "checking"), (second, "savings")] {
+            let total: i64 = ["checking", "savings"]
+                .iter()
+                .map(|key| tx_mgr.read_operation(tx_id, &key.to_string()).unwrap().unwrap().parse::<i64>().unwrap())
+                .sum();
+            assert!(total >= 100);
+            tx_mgr.increment(tx_id, account.to_string(), -100).unwrap();
+        }
+        tx_mgr.commit_transaction(first).unwrap();
+        assert!(matches!(tx_mgr.commit_transaction(second), Err(Error::SerializationFailure { .. })));
+        assert_eq!(tx_mgr.read_committed(&"savings".to_string()), Some("80".to_string()));
+    }
+
+    #[test]
+    fn test_untracked_reads_are_not_validated_at_commit() {
+        let tx_mgr = TxnMgr::new();
+        let tx_id = begin_serializable(&tx_mgr);
+        assert_eq!(tx_mgr.read_untracked(tx_id, &"x".to_string()), Ok(None));
+        tx_mgr.execute_write_operation(tx_id, "y".to_string(), "1".to_string()).unwrap();
+
+        let other = tx_mgr.begin_transaction().unwrap();
+        tx_mgr.execute_write_operation(other, "x".to_string(), "1".to_string()).unwrap();
+        tx_mgr.commit_transaction(other).unwrap();
+        tx_mgr.commit_transaction(tx_id).unwrap();
+    }
+
+    #[test]
+    fn test_serializable_detects_phantom_inserts() {
+        // 每个事务统计当天的预约数，少于 1 个时插入一个新预约；插入的键之前不存在
+        let tx_mgr = TxnMgr::new();
+        let first = begin_serializable(&tx_mgr);
+        let second = begin_serializable(&tx_mgr);
+        for (tx_id, key) in [(first, "booking/day1/a"), (second, "booking/day1/b")] {
+            assert!(tx_mgr.scan(tx_id, &KeyRange::prefix("booking/day1/"), 10).unwrap().is_empty());
+            tx_mgr.execute_write_operation(tx_id, key.to_string(), "booked".to_string()).unwrap();
+        }
+        tx_mgr.commit_transaction(first).unwrap();
+        assert_eq!(
+            tx_mgr.commit_transaction(second),
+            Err(Error::SerializationFailure { transaction_id: second, key: "booking/day1/a".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_serializable_allows_disjoint_transactions() {
+        let tx_mgr = on_call_roster();
+        let first = begin_serializable(&tx_mgr);
+        let second = begin_serializable(&tx_mgr);
+        let reader = tx_mgr.begin_transaction_with_options(BeginOptions {
+            mode: TransactionMode::ReadOnly,
+            isolation: IsolationLevel::Serializable,
+            ..tx_mgr.default_begin_options()
+        }).unwrap();
+
+        // 读集与其他事务的写入不相交
+        assert_eq!(tx_mgr.read_operation(first, &"on_call/alice".to_string()), Ok(Some("yes".to_string())));
+        tx_mgr.execute_write_operation(first, "on_call/bob".to_string(), "no".to_string()).unwrap();
+        // 分页扫描只记录实际读到的部分，之后的键被修改不影响本事务
+        let page = tx_mgr.scan(second, &KeyRange::prefix("on_call/"), 1).unwrap();
+        assert_eq!(page, vec![("on_call/alice".to_string(), "yes".to_string())]);
+        tx_mgr.execute_write_operation(second, "audit".to_string(), "checked".to_string()).unwrap();
+        let _ = tx_mgr.read_operation(reader, &"on_call/alice".to_string()).unwrap();
+
+        let third = begin_serializable(&tx_mgr);
+        assert_eq!(tx_mgr.read_operation(third, &"on_call/bob".to_string()), Ok(Some("yes".to_string())));
+        tx_mgr.execute_write_operation(third, "on_call/carol".to_string(), "yes".to_string()).unwrap();
+
+        // third 读过的 bob 在它提交之后才被 first 修改，按提交顺序 third 排在 first 之前
+        tx_mgr.commit_transaction(third).unwrap();
+        tx_mgr.commit_transaction(first).unwrap();
+        tx_mgr.commit_transaction(reader).unwrap();
+        tx_mgr.commit_transaction(second).unwrap();
+    }
+}
@@ -1,17 +1,26 @@
 use axum::{
     extract::{Path, State}, // 从这里移除 Query
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post, MethodRouter},
     Json, Router,
 };
 use axum::extract::Query; // 单独导入 Query
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
 // use tokio::net::TcpListener; // 确保此行被注释或删除
-use crate::api::ApiMgr;
+use crate::api::{ApiMgr, BatchOp, BatchOpResult};
+use crate::storage::{GcStats, KeyRange, RetentionPolicy, Version};
+use crate::error::Error;
+use crate::transaction::{ChangeEvent, GcMetrics, IsolationLevel, SnapshotInfo, TransactionMode};
+use futures_util::stream::{self, StreamExt};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
 
 // 1. 定义请求/响应结构体
 #[derive(Serialize, Deserialize, Debug)]
@@ -19,6 +28,7 @@ pub struct PutRequest {
     key: String,
     value: String,
     transaction_id: Option<u64>, // 添加可选的 transaction_id 字段
+    ttl_seconds: Option<u64>, // 可选的过期时间 (秒)
 }
 
 #[derive(Serialize, Debug)]
@@ -36,9 +46,83 @@ pub struct GetValueResponse {
     value: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ScanParams {
+    prefix: Option<String>,
+    start: Option<String>, // 包含在内的起始键
+    end: Option<String>, // 不包含在内的结束键
+    limit: Option<usize>,
+    cursor: Option<String>, // 上一页返回的 next_cursor，从它之后继续扫描
+    transaction_id: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ScanResponse {
+    items: Vec<GetValueResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>, // 还有更多数据时返回，作为下一页的 cursor
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BatchRequest {
+    operations: Vec<BatchOp>,
+    transaction_id: Option<u64>,
+}
+
+// 条件写入：expected 为 null 或省略表示要求键不存在 (put-if-absent)，value 为 null 或省略表示删除 (delete-if-equals)
+#[derive(Deserialize, Debug)]
+pub struct CompareAndSwapRequest {
+    key: String,
+    expected: Option<String>,
+    value: Option<String>,
+    transaction_id: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct IncrementRequest {
+    #[serde(default = "default_increment_delta")]
+    delta: i64,
+    transaction_id: Option<u64>,
+}
+
+fn default_increment_delta() -> i64 {
+    1
+}
+
+#[derive(Serialize, Debug)]
+pub struct IncrementResponse {
+    key: String,
+    value: i64,
+}
+
+// 订阅单个键 (key) 或前缀 (prefix)；since 为提交时间戳，从它之后的变更开始推送
+#[derive(Deserialize, Debug)]
+pub struct WatchParams {
+    key: Option<String>,
+    prefix: Option<String>,
+    since: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeleteResponse {
+    key: String,
+    existed: bool, // 删除前该键是否存在
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TransactionResponse {
     transaction_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout_seconds: Option<u64>, // 事务的超时时间，超时未提交时会被自动回滚
+    mode: TransactionMode,
+    isolation: IsolationLevel,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct BeginTransactionRequest {
+    timeout_seconds: Option<u64>, // 覆盖默认的超时时间，0 表示不超时
+    mode: Option<TransactionMode>, // "read_write" (默认) 或 "read_only"
+    isolation: Option<IsolationLevel>, // "read_committed"、"snapshot" (默认) 或 "serializable"
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -46,17 +130,107 @@ pub struct TransactionActionRequest {
     transaction_id: u64,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateSavepointRequest {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateSnapshotRequest {
+    snapshot_id: String,
+}
+
+// 数据目录，可通过 SKDB_DATA_DIR 环境变量覆盖
+const DEFAULT_DATA_DIR: &str = "skdb_data";
+// 扫描每页的默认和最大条数
+const DEFAULT_SCAN_LIMIT: usize = 100;
+const MAX_SCAN_LIMIT: usize = 1000;
+// 后台垃圾回收的间隔 (秒)，可通过 SKDB_GC_INTERVAL_SECS 覆盖，设为 0 时关闭后台回收
+const DEFAULT_GC_INTERVAL_SECS: u64 = 60;
+// 事务的默认超时时间 (秒)，可通过 SKDB_TXN_TIMEOUT_SECS 覆盖，设为 0 时事务默认不超时
+const DEFAULT_TXN_TIMEOUT_SECS: u64 = 300;
+// 后台回滚超时事务的间隔 (秒)
+const TXN_REAPER_INTERVAL_SECS: u64 = 1;
+// 后台清除过期键的间隔 (秒)，可通过 SKDB_EXPIRY_INTERVAL_SECS 覆盖，设为 0 时关闭
+const DEFAULT_EXPIRY_INTERVAL_SECS: u64 = 5;
+
+// 读取数字类型的环境变量，未设置或格式错误时返回 None
+fn env_u64(name: &str) -> Option<u64> {
+    let raw = std::env::var(name).ok()?;
+    match raw.parse() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            eprintln!("Ignoring invalid value '{}' for {}", raw, name);
+            None
+        }
+    }
+}
+
+// 保留策略：SKDB_KEEP_VERSIONS 为每个键保留的版本数，SKDB_KEEP_VERSIONS_SECS 为按时间保留的秒数
+fn retention_policy_from_env() -> RetentionPolicy {
+    let mut policy = RetentionPolicy::default();
+    if let Some(keep_last) = env_u64("SKDB_KEEP_VERSIONS") {
+        policy.keep_last = keep_last as usize;
+    }
+    policy.keep_newer_than = env_u64("SKDB_KEEP_VERSIONS_SECS").map(Duration::from_secs);
+    policy
+}
+
 pub async fn start_server() {
-    let api_mgr = Arc::new(Mutex::new(ApiMgr::new()));
+    let data_dir = std::env::var("SKDB_DATA_DIR").unwrap_or_else(|_| DEFAULT_DATA_DIR.to_string());
+    let api_mgr = match ApiMgr::open(&data_dir) {
+        Ok(mgr) => Arc::new(mgr),
+        Err(e) => {
+            eprintln!("Failed to open data directory '{}': {}", data_dir, e);
+            return;
+        }
+    };
+    let txn_timeout_secs = env_u64("SKDB_TXN_TIMEOUT_SECS").unwrap_or(DEFAULT_TXN_TIMEOUT_SECS);
+    api_mgr.set_retention_policy(retention_policy_from_env());
+    api_mgr.set_default_transaction_timeout((txn_timeout_secs > 0).then(|| Duration::from_secs(txn_timeout_secs)));
+
+    // 后台压缩任务：定期按保留策略回收历史版本
+    let gc_interval_secs = env_u64("SKDB_GC_INTERVAL_SECS").unwrap_or(DEFAULT_GC_INTERVAL_SECS);
+    spawn_periodic(&api_mgr, "GC", gc_interval_secs, |manager| {
+        manager.collect_garbage();
+    });
+    // 后台回滚客户端已经放弃的超时事务
+    spawn_periodic(&api_mgr, "transaction reaper", TXN_REAPER_INTERVAL_SECS, |manager| {
+        manager.reap_expired_transactions();
+    });
+    // 后台清除过期键 (读取时已经看不到它们，这里只是写入删除标记以便之后被回收)
+    let expiry_interval_secs = env_u64("SKDB_EXPIRY_INTERVAL_SECS").unwrap_or(DEFAULT_EXPIRY_INTERVAL_SECS);
+    spawn_periodic(&api_mgr, "expiry", expiry_interval_secs, |manager| {
+        if let Err(e) = manager.evict_expired() {
+            eprintln!("Failed to evict expired keys: {}", e);
+        }
+    });
 
     // 定义路由
     let app = Router::new()
         .route("/", get(root_handler))
-        .route("/api/kv/:key", get(get_value_handler)) // 2. GET 端点
-        .route("/api/kv", post(put_value_handler))    // 3. POST 端点
+        .route("/api/kv/:key", get(get_value_handler).delete(delete_value_handler)) // 2. GET 端点
+        .route("/api/kv/:key/history", get(get_history_handler))
+        .route("/api/kv/:key/increment", post(increment_handler))
+        .route("/api/kv", get(scan_handler).post(put_value_handler))    // 3. POST 端点
+        // 静态路由优先于 /api/kv/:key 匹配，这里的 GET/DELETE 仍然读取和删除名为 "batch"、"cas" 的键 (见 key_route)
+        .route("/api/kv/batch", key_route("batch").post(batch_handler))
+        .route("/api/kv/cas", key_route("cas").post(compare_and_swap_handler))
         .route("/api/transactions/begin", post(begin_transaction_handler))
         .route("/api/transactions/commit", post(commit_transaction_handler))
         .route("/api/transactions/rollback", post(rollback_transaction_handler))
+        .route(
+            "/api/transactions/:transaction_id/savepoints",
+            get(list_savepoints_handler).post(create_savepoint_handler),
+        )
+        .route("/api/transactions/:transaction_id/savepoints/:name", delete(release_savepoint_handler))
+        .route("/api/transactions/:transaction_id/savepoints/:name/rollback", post(rollback_to_savepoint_handler))
+        .route("/api/snapshots", get(list_snapshots_handler).post(create_snapshot_handler))
+        .route("/api/snapshots/:snapshot_id", delete(delete_snapshot_handler))
+        .route("/api/snapshots/:snapshot_id/restore", post(restore_snapshot_handler))
+        .route("/api/watch", get(watch_handler))
+        .route("/api/gc", post(run_gc_handler))
+        .route("/api/gc/metrics", get(gc_metrics_handler))
         .with_state(api_mgr); // 5. 共享状态
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 4399));
@@ -76,412 +250,794 @@ pub async fn start_server() {
     }
 }
 
+// 每隔 interval_secs 秒在阻塞线程池中执行一次 task；interval_secs 为 0 时不启动
+fn spawn_periodic(api_mgr: &Arc<ApiMgr>, name: &'static str, interval_secs: u64, task: fn(&ApiMgr)) {
+    if interval_secs == 0 {
+        return;
+    }
+    let state = Arc::clone(api_mgr);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        interval.tick().await; // 第一次 tick 立即返回，跳过它
+        loop {
+            interval.tick().await;
+            let state = Arc::clone(&state);
+            if let Err(e) = tokio::task::spawn_blocking(move || task(&state)).await {
+                eprintln!("Background {} task failed: {}", name, e);
+            }
+        }
+    });
+}
+
+// /api/kv/:key 上与静态路由同名的键：把 GET/DELETE 转给键的处理函数，
+// 否则对名为 key 的键的读取和删除会被静态路由接住并返回 405
+fn key_route(key: &'static str) -> MethodRouter<Arc<ApiMgr>> {
+    get(move |state, query| get_value_handler(state, Path(key.to_string()), query))
+        .delete(move |state, query| delete_value_handler(state, Path(key.to_string()), query))
+}
+
+// 访问数据库的请求可能要等待锁：修改状态的请求等待提交锁 (持有它的提交正在 fsync)，
+// 读取等待应用提交或垃圾回收时持有的存储写锁，因此都在阻塞线程池中执行，不占用处理其他请求的异步工作线程
+async fn run_blocking<F>(app_state: Arc<ApiMgr>, op: F) -> axum::response::Response
+where
+    F: FnOnce(&ApiMgr) -> axum::response::Response + Send + 'static,
+{
+    match tokio::task::spawn_blocking(move || op(&app_state)).await {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Request handler failed: {}", e);
+            internal_error_response()
+        }
+    }
+}
+
 async fn root_handler() -> &'static str {
     "数据库 API 服务器已启动"
 }
 
 // 2. 实现 GET /api/kv/:key 端点
 async fn get_value_handler(
-    State(app_state): State<Arc<Mutex<ApiMgr>>>,
+    State(app_state): State<Arc<ApiMgr>>,
     Path(key): Path<String>,
     Query(params): Query<HashMap<String, String>>, // 添加 Query 提取器
 ) -> impl IntoResponse {
-    let transaction_id_str = params.get("transaction_id");
-    println!(
-        "Received GET request for key: {}, transaction_id_str: {:?}",
-        key, transaction_id_str
-    );
+    println!("Received GET request for key: {}, params: {:?}", key, params);
 
-    let mut manager = match app_state.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => {
-            eprintln!("Failed to acquire lock for ApiMgr: {:?}", poisoned);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::<GetValueResponse> {
-                    success: false,
-                    data: None,
-                    error: Some("Failed to acquire lock".to_string()),
-                }),
-            )
-                .into_response();
+    // 解析 transaction_id / as_of / as_of_transaction (如果提供)
+    let mut parsed = HashMap::new();
+    for name in ["transaction_id", "as_of", "as_of_transaction"] {
+        if let Some(raw) = params.get(name) {
+            match raw.parse::<u64>() {
+                Ok(number) => {
+                    parsed.insert(name, number);
+                }
+                Err(_) => return error_response(&Error::Parse(format!("invalid {} '{}'", name, raw))),
+            }
         }
-    };
+    }
+    if parsed.len() > 1 {
+        let message = "only one of transaction_id, as_of and as_of_transaction may be given";
+        return error_response(&Error::InvalidArgument(message.to_string()));
+    }
 
-    // 解析 transaction_id (如果提供)
-    let transaction_id: Option<u64> = match transaction_id_str {
-        Some(id_str) => match id_str.parse::<u64>() {
-            Ok(id) => Some(id),
-            Err(_) => {
-                return (
-                    StatusCode::BAD_REQUEST,
+    run_blocking(app_state, move |manager| {
+        // 提供了 transaction_id 时在该事务的快照中读取，as_of / as_of_transaction 做时间点读取，否则读取最新提交的数据
+        let result = if let Some(&tid) = parsed.get("transaction_id") {
+            println!("Getting value for key '{}' under transaction_id {}", key, tid);
+            manager.get_in_transaction(tid, key.clone())
+        } else if let Some(&ts) = parsed.get("as_of") {
+            manager.get_as_of(key.clone(), ts)
+        } else if let Some(&tid) = parsed.get("as_of_transaction") {
+            manager.get_as_of_transaction(key.clone(), tid)
+        } else {
+            println!("Getting value for key '{}' (no transaction_id)", key);
+            Ok(manager.get(key.clone()))
+        };
+        let value = match result {
+            Ok(value) => value,
+            Err(e) => {
+                eprintln!("Failed to get value for key '{}': {}", key, e);
+                return error_response(&e);
+            }
+        };
+
+        match value {
+            Some(value) => {
+                println!("Value found for key '{}': '{}'", key, value);
+                (
+                    StatusCode::OK,
+                    Json(ApiResponse {
+                        success: true,
+                        data: Some(GetValueResponse { key, value }),
+                        error: None,
+                    }),
+                )
+                    .into_response()
+            }
+            None => {
+                println!("No value found for key '{}'", key);
+                (
+                    StatusCode::OK,
                     Json(ApiResponse::<GetValueResponse> {
-                        success: false,
+                        success: true,
                         data: None,
-                        error: Some("Invalid transaction_id format".to_string()),
+                        error: None,
                     }),
                 )
-                    .into_response();
+                    .into_response()
             }
-        },
-        None => None,
-    };
+        }
+    })
+    .await
+}
 
-    if let Some(tid) = transaction_id {
-        // 如果提供了 transaction_id，我们假设 ApiMgr::get 能够处理它
-        // 或者，如果 ApiMgr::get 尚未感知事务，它将读取最新提交的数据。
-        // 这里的重点是将 transaction_id 从 API 层传递下去。
-        println!("Getting value for key '{}' under transaction_id {}", key, tid);
-        // 注意：当前 ApiMgr::get 可能不接受 transaction_id。
-        // 为了简化，我们仍然调用现有的 manager.get(key)。
-        // 理想情况下，ApiMgr::get 应该能够感知事务上下文。
-    } else {
-        println!("Getting value for key '{}' (no transaction_id)", key);
-    }
+// 3. 实现 POST /api/kv 端点
+async fn put_value_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Json(payload): Json<PutRequest>,
+) -> impl IntoResponse {
+    println!(
+        "Received POST request with key: '{}', value: '{}', transaction_id: {:?}",
+        payload.key, payload.value, payload.transaction_id
+    );
+
+    run_blocking(app_state, move |manager| {
+        if let Some(provided_transaction_id) = payload.transaction_id {
+            // 情况 1: 提供了 transaction_id
+            println!(
+                "Putting value for key '{}' under transaction_id {}",
+                payload.key, provided_transaction_id
+            );
+            let ttl = payload.ttl_seconds.map(Duration::from_secs);
+            match manager.put_with_ttl(provided_transaction_id, payload.key.clone(), payload.value.clone(), ttl) {
+                Ok(()) => {
+                    println!(
+                        "Put operation for key '{}' in transaction {} successful",
+                        payload.key, provided_transaction_id
+                    );
+                    (
+                        StatusCode::OK,
+                        Json(ApiResponse::<()> {
+                            success: true,
+                            data: None,
+                            error: None,
+                        }),
+                    )
+                        .into_response()
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Failed to put value for key '{}' in transaction {}: {}",
+                        payload.key, provided_transaction_id, e
+                    );
+                    error_response(&e)
+                }
+            }
+        } else {
+            // 情况 2: 没有提供 transaction_id (自动提交模式)
+            println!(
+                "Putting value for key '{}' in auto-commit mode",
+                payload.key
+            );
+            let transaction_id = match manager.begin_transaction() {
+                Ok(transaction_id) => transaction_id,
+                Err(e) => {
+                    eprintln!("Failed to begin auto-commit transaction for key '{}': {}", payload.key, e);
+                    return error_response(&e);
+                }
+            };
+            println!("Started transaction with ID: {} for auto-commit", transaction_id);
+
+            let result = manager
+                .put_with_ttl(transaction_id, payload.key.clone(), payload.value.clone(), payload.ttl_seconds.map(Duration::from_secs))
+                .and_then(|_| manager.commit_transaction(transaction_id));
+            if let Err(e) = result {
+                eprintln!(
+                    "Failed to put value for key '{}' in auto-commit transaction {}: {}",
+                    payload.key, transaction_id, e
+                );
+                // 尝试回滚 (事务可能已经结束，此时忽略错误)
+                let _ = manager.rollback_transaction(transaction_id);
+                return error_response(&e);
+            }
+            println!("Committed auto-commit transaction {}", transaction_id);
 
-    match manager.get(key.clone()) {
-        Some(value) => {
-            println!("Value found for key '{}': '{}'", key, value);
             (
                 StatusCode::OK,
-                Json(ApiResponse {
+                Json(ApiResponse::<()> {
                     success: true,
-                    data: Some(GetValueResponse { key, value }),
+                    data: None,
                     error: None,
                 }),
             )
                 .into_response()
         }
-        None => {
-            println!("No value found for key '{}'", key);
-            (
+    })
+    .await
+}
+
+// Handler for DELETE /api/kv/:key?transaction_id=
+// 提供 transaction_id 时删除在该事务中进行，否则自动提交
+async fn delete_value_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path(key): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    println!("Received DELETE request for key: {}, params: {:?}", key, params);
+    let transaction_id = match params.get("transaction_id").map(|raw| raw.parse::<u64>()) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => {
+            let raw = &params["transaction_id"];
+            return error_response(&Error::Parse(format!("invalid transaction_id '{}'", raw)));
+        }
+        None => None,
+    };
+
+    run_blocking(app_state, move |manager| {
+        match manager.delete(transaction_id, key.clone()) {
+            Ok(existed) => (
                 StatusCode::OK,
-                Json(ApiResponse::<GetValueResponse> {
+                Json(ApiResponse {
                     success: true,
-                    data: None,
+                    data: Some(DeleteResponse { key, existed }),
                     error: None,
                 }),
             )
-                .into_response()
+                .into_response(),
+            Err(e) => {
+                eprintln!("Failed to delete key '{}' in transaction {:?}: {}", key, transaction_id, e);
+                error_response(&e)
+            }
         }
-    }
+    })
+    .await
 }
 
-// 3. 实现 POST /api/kv 端点
-async fn put_value_handler(
-    State(app_state): State<Arc<Mutex<ApiMgr>>>,
-    Json(payload): Json<PutRequest>,
+// Handler for POST /api/kv/batch：在一个事务中原子地执行一组 put/delete
+async fn batch_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Json(payload): Json<BatchRequest>,
 ) -> impl IntoResponse {
     println!(
-        "Received POST request with key: '{}', value: '{}', transaction_id: {:?}",
-        payload.key, payload.value, payload.transaction_id
+        "Received batch request with {} operation(s), transaction_id: {:?}",
+        payload.operations.len(),
+        payload.transaction_id
     );
+    run_blocking(app_state, move |manager| {
+        match manager.put_many(payload.transaction_id, payload.operations) {
+            Ok(results) => (
+                StatusCode::OK,
+                Json(ApiResponse::<Vec<BatchOpResult>> {
+                    success: true,
+                    data: Some(results),
+                    error: None,
+                }),
+            )
+                .into_response(),
+            Err(e) => {
+                eprintln!("Failed to apply batch in transaction {:?}: {}", payload.transaction_id, e);
+                error_response(&e)
+            }
+        }
+    })
+    .await
+}
 
-    let mut manager = match app_state.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => {
-            eprintln!("Failed to acquire lock for ApiMgr: {:?}", poisoned);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
+// Handler for POST /api/kv/cas：前提不成立时返回 412
+async fn compare_and_swap_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Json(payload): Json<CompareAndSwapRequest>,
+) -> impl IntoResponse {
+    println!("Received compare-and-swap request: {:?}", payload);
+    run_blocking(app_state, move |manager| {
+        match manager.compare_and_swap(payload.transaction_id, payload.key.clone(), payload.expected, payload.value) {
+            Ok(()) => (
+                StatusCode::OK,
                 Json(ApiResponse::<()> {
-                    success: false,
+                    success: true,
                     data: None,
-                    error: Some("Failed to acquire lock".to_string()),
+                    error: None,
+                }),
+            )
+                .into_response(),
+            Err(e) => {
+                eprintln!("Compare-and-swap on key '{}' failed: {}", payload.key, e);
+                error_response(&e)
+            }
+        }
+    })
+    .await
+}
+
+// Handler for POST /api/kv/:key/increment
+async fn increment_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path(key): Path<String>,
+    Json(payload): Json<IncrementRequest>,
+) -> impl IntoResponse {
+    println!("Received increment request for key: {}, {:?}", key, payload);
+    run_blocking(app_state, move |manager| {
+        match manager.increment(payload.transaction_id, key.clone(), payload.delta) {
+            Ok(value) => (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    success: true,
+                    data: Some(IncrementResponse { key, value }),
+                    error: None,
                 }),
             )
-                .into_response();
+                .into_response(),
+            Err(e) => {
+                eprintln!("Increment of key '{}' failed: {}", key, e);
+                error_response(&e)
+            }
         }
+    })
+    .await
+}
+
+// Handler for GET /api/watch?key=|prefix=&since=
+// 以 Server-Sent Events 推送已提交的变更：每次提交一个 commit 事件，事件 ID 为提交时间戳，
+// 断线后客户端可以用 since (或 Last-Event-ID 请求头) 从最后收到的提交时间戳继续订阅
+async fn watch_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    headers: HeaderMap,
+    Query(params): Query<WatchParams>,
+) -> axum::response::Response {
+    println!("Received watch request: {:?}", params);
+    let range = match (params.key, params.prefix) {
+        (Some(key), None) => KeyRange::single(&key),
+        (None, Some(prefix)) => KeyRange::prefix(&prefix),
+        _ => return error_response(&Error::InvalidArgument("exactly one of key and prefix must be given".to_string())),
     };
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    let since = params.since.or(last_event_id);
 
-    if let Some(provided_transaction_id) = payload.transaction_id {
-        // 情况 1: 提供了 transaction_id
-        println!(
-            "Putting value for key '{}' under transaction_id {}",
-            payload.key, provided_transaction_id
-        );
-        // 假设 ApiMgr::put 能够处理事务 ID
-        // 并且在失败时会 panic 或返回一个我们可以转换为 ApiResponse 的错误
-        // 为了简化，我们假设它在成功时返回 (), 失败时 panic
-        // 实际应用中，ApiMgr::put 应该返回 Result
-        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            manager.put(provided_transaction_id, payload.key.clone(), payload.value.clone())
-        })) {
-            Ok(_) => {
-                println!(
-                    "Put operation for key '{}' in transaction {} successful",
-                    payload.key, provided_transaction_id
-                );
+    // 订阅时要短暂持有提交锁，同样放到阻塞线程池中
+    let subscription = {
+        let range = range.clone();
+        tokio::task::spawn_blocking(move || app_state.watch(&range, since)).await
+    };
+    let (replay, receiver) = match subscription {
+        Ok(Ok(subscription)) => subscription,
+        Ok(Err(e)) => {
+            eprintln!("Failed to watch {:?}: {}", range, e);
+            return error_response(&e);
+        }
+        Err(e) => {
+            eprintln!("Failed to watch {:?}: {}", range, e);
+            return internal_error_response();
+        }
+    };
+
+    // 先重放历史变更，再转发订阅之后的提交；落后太多时发送 lagged 事件并结束，由客户端重新订阅
+    let live = stream::unfold(Some((receiver, range)), |state| async move {
+        let (mut receiver, range) = state?;
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if let Some(event) = event.filter(&range) {
+                        return Some((commit_event(&event), Some((receiver, range))));
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    let event = Event::default().event("lagged").data(format!("{} change(s) were dropped", skipped));
+                    return Some((event, None));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = stream::iter(replay.iter().map(commit_event).collect::<Vec<_>>()).chain(live);
+    Sse::new(events.map(Ok::<Event, Infallible>)).keep_alive(KeepAlive::default()).into_response()
+}
+
+fn commit_event(event: &ChangeEvent) -> Event {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    Event::default().event("commit").id(event.commit_ts.to_string()).data(data)
+}
+
+// Handler for POST /api/transactions/begin
+// 请求体可选：{"timeout_seconds": N, "mode": "read_only", "isolation": "read_committed"}，省略的字段使用默认值
+async fn begin_transaction_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    payload: Option<Json<BeginTransactionRequest>>,
+) -> impl IntoResponse {
+    let Json(payload) = payload.unwrap_or_default();
+    println!("Received request to begin transaction: {:?}", payload);
+    run_blocking(app_state, move |manager| {
+        let mut options = manager.default_begin_options();
+        match payload.timeout_seconds {
+            Some(0) => options.timeout = None,
+            Some(seconds) => options.timeout = Some(Duration::from_secs(seconds)),
+            None => {}
+        }
+        options.mode = payload.mode.unwrap_or(options.mode);
+        options.isolation = payload.isolation.unwrap_or(options.isolation);
+        match manager.begin_transaction_with_options(options) {
+            Ok(transaction_id) => {
+                println!("Transaction begun with ID: {}", transaction_id);
                 (
                     StatusCode::OK,
-                    Json(ApiResponse::<()> {
+                    Json(ApiResponse {
                         success: true,
-                        data: None,
+                        data: Some(TransactionResponse {
+                            transaction_id,
+                            timeout_seconds: options.timeout.map(|timeout| timeout.as_secs()),
+                            mode: options.mode,
+                            isolation: options.isolation,
+                        }),
                         error: None,
                     }),
                 )
                     .into_response()
             }
-            Err(_) => {
-                eprintln!(
-                    "Failed to put value for key '{}' in transaction {} (panic occurred)",
-                    payload.key, provided_transaction_id
-                );
+            Err(e) => {
+                eprintln!("Failed to begin transaction: {}", e);
+                error_response(&e)
+            }
+        }
+    })
+    .await
+}
+
+// Handler for POST /api/transactions/commit
+async fn commit_transaction_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Json(payload): Json<TransactionActionRequest>,
+) -> impl IntoResponse {
+    println!("Received request to commit transaction ID: {}", payload.transaction_id);
+    run_blocking(app_state, move |manager| {
+        match manager.commit_transaction(payload.transaction_id) {
+            Ok(()) => {
+                println!("Transaction {} committed successfully", payload.transaction_id);
                 (
-                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StatusCode::OK,
                     Json(ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some(format!(
-                            "Failed to put value in transaction {}",
-                            provided_transaction_id
-                        )),
+                        success: true,
+                        data: None, // Or Some("Transaction committed".to_string())
+                        error: None,
                     }),
                 )
                     .into_response()
             }
+            Err(e) => {
+                eprintln!("Failed to commit transaction {}: {}", payload.transaction_id, e);
+                error_response(&e)
+            }
         }
-    } else {
-        // 情况 2: 没有提供 transaction_id (自动提交模式)
-        println!(
-            "Putting value for key '{}' in auto-commit mode",
-            payload.key
-        );
-        // 这里的错误处理逻辑基于现有的代码结构，依赖 panic 或成功
-        // 理想情况下，每个 ApiMgr 调用都应返回 Result
-        let transaction_id = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| manager.begin_transaction())) {
-            Ok(id) => id,
-            Err(_) => {
-                eprintln!("Failed to begin transaction for auto-commit (panic occurred)");
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
+    })
+    .await
+}
+
+// Handler for POST /api/transactions/rollback
+async fn rollback_transaction_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Json(payload): Json<TransactionActionRequest>,
+) -> impl IntoResponse {
+    println!("Received request to rollback transaction ID: {}", payload.transaction_id);
+    run_blocking(app_state, move |manager| {
+        match manager.rollback_transaction(payload.transaction_id) {
+            Ok(()) => {
+                println!("Transaction {} rollbacked successfully", payload.transaction_id);
+                (
+                    StatusCode::OK,
                     Json(ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Failed to begin transaction for auto-commit".to_string()),
+                        success: true,
+                        data: None, // Or Some("Transaction rollbacked".to_string())
+                        error: None,
                     }),
                 )
-                    .into_response();
+                    .into_response()
+            }
+            Err(e) => {
+                eprintln!("Failed to rollback transaction {}: {}", payload.transaction_id, e);
+                error_response(&e)
             }
-        };
-        println!("Started transaction with ID: {} for auto-commit", transaction_id);
-
-        if let Err(_) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            manager.put(transaction_id, payload.key.clone(), payload.value.clone())
-        })) {
-            eprintln!(
-                "Failed to put value for key '{}' in auto-commit transaction {} (panic occurred)",
-                payload.key, transaction_id
-            );
-            // 尝试回滚
-            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| manager.rollback_transaction(transaction_id)));
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!(
-                        "Failed to put value in auto-commit transaction {}",
-                        transaction_id
-                    )),
-                }),
-            )
-                .into_response();
-        }
-        println!(
-            "Put operation for key '{}' in auto-commit transaction {} successful",
-            payload.key, transaction_id
-        );
-
-        if let Err(_) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| manager.commit_transaction(transaction_id))) {
-            eprintln!(
-                "Failed to commit auto-commit transaction {} (panic occurred)",
-                transaction_id
-            );
-            // 尝试回滚，尽管提交失败后的回滚可能意义不大
-            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| manager.rollback_transaction(transaction_id)));
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Failed to commit auto-commit transaction {}", transaction_id)),
-                }),
-            )
-                .into_response();
         }
-        println!("Committed auto-commit transaction {}", transaction_id);
+    })
+    .await
+}
 
+// Handler for GET /api/kv?prefix=&start=&end=&limit=&cursor=&transaction_id=
+async fn scan_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Query(params): Query<ScanParams>,
+) -> impl IntoResponse {
+    println!("Received scan request: {:?}", params);
+    let limit = params.limit.unwrap_or(DEFAULT_SCAN_LIMIT).clamp(1, MAX_SCAN_LIMIT);
+    let mut range = KeyRange {
+        start: params.start.map_or(Bound::Unbounded, Bound::Included),
+        end: params.end.map_or(Bound::Unbounded, Bound::Excluded),
+        prefix: params.prefix.unwrap_or_default(),
+    };
+    if let Some(cursor) = params.cursor {
+        range.start = Bound::Excluded(cursor);
+    }
+
+    run_blocking(app_state, move |manager| {
+        // 多取一条用来判断是否还有下一页
+        let mut pairs = match manager.scan(params.transaction_id, &range, limit + 1) {
+            Ok(pairs) => pairs,
+            Err(e) => {
+                eprintln!("Failed to scan {:?}: {}", range, e);
+                return error_response(&e);
+            }
+        };
+        let next_cursor = if pairs.len() > limit {
+            pairs.truncate(limit);
+            pairs.last().map(|(key, _)| key.clone())
+        } else {
+            None
+        };
+        let items = pairs.into_iter().map(|(key, value)| GetValueResponse { key, value }).collect();
         (
             StatusCode::OK,
-            Json(ApiResponse::<()> {
+            Json(ApiResponse {
                 success: true,
-                data: None,
+                data: Some(ScanResponse { items, next_cursor }),
                 error: None,
             }),
         )
             .into_response()
-    }
+    })
+    .await
 }
 
-// Handler for POST /api/transactions/begin
-async fn begin_transaction_handler(
-    State(app_state): State<Arc<Mutex<ApiMgr>>>,
+// Handler for GET /api/kv/:key/history
+async fn get_history_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path(key): Path<String>,
 ) -> impl IntoResponse {
-    println!("Received request to begin transaction");
-    let mut manager = match app_state.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => {
-            eprintln!("Failed to acquire lock for ApiMgr: {:?}", poisoned);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::<TransactionResponse> {
-                    success: false,
-                    data: None,
-                    error: Some("Failed to acquire lock".to_string()),
-                }),
-            )
-                .into_response();
-        }
-    };
+    println!("Received history request for key: {}", key);
+    run_blocking(app_state, move |manager| {
+        (
+            StatusCode::OK,
+            Json(ApiResponse::<Vec<Version>> {
+                success: true,
+                data: Some(manager.history(key)),
+                error: None,
+            }),
+        )
+            .into_response()
+    })
+    .await
+}
 
-    // 在实际应用中，ApiMgr::begin_transaction 应该返回 Result
-    // 这里我们假设它在成功时返回 ID，失败时 panic 或通过其他方式处理
-    // 根据任务要求，如果 ApiMgr 方法尚未返回 Result，则可以继续依赖现有的 panic 行为
-    // 或者在处理器中添加基本的错误检查。
-    // 为了简化，我们这里直接调用，并期望 Axum 捕获 panic。
-    // 更健壮的实现会处理 Result。
-    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| manager.begin_transaction())) {
-        Ok(transaction_id) => {
-            println!("Transaction begun with ID: {}", transaction_id);
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    success: true,
-                    data: Some(TransactionResponse { transaction_id }),
-                    error: None,
-                }),
-            )
-                .into_response()
-        }
-        Err(_) => {
-            eprintln!("Failed to begin transaction (panic occurred)");
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::<TransactionResponse> {
-                    success: false,
-                    data: None,
-                    error: Some("Failed to begin transaction".to_string()),
-                }),
-            )
-                .into_response()
+// Handler for POST /api/gc：立即执行一次垃圾回收
+async fn run_gc_handler(State(app_state): State<Arc<ApiMgr>>) -> impl IntoResponse {
+    run_blocking(app_state, move |manager| {
+        (
+            StatusCode::OK,
+            Json(ApiResponse::<GcStats> {
+                success: true,
+                data: Some(manager.collect_garbage()),
+                error: None,
+            }),
+        )
+            .into_response()
+    })
+    .await
+}
+
+// Handler for GET /api/gc/metrics
+async fn gc_metrics_handler(State(manager): State<Arc<ApiMgr>>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        Json(ApiResponse::<GcMetrics> {
+            success: true,
+            data: Some(manager.gc_metrics()),
+            error: None,
+        }),
+    )
+        .into_response()
+}
+
+// 处理请求的任务失败 (panic) 时返回的响应
+fn internal_error_response() -> axum::response::Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some("Internal server error".to_string()),
+        }),
+    )
+        .into_response()
+}
+
+// 保存点操作的统一响应
+fn savepoint_response(result: Result<(), Error>) -> axum::response::Response {
+    match result {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(ApiResponse::<()> {
+                success: true,
+                data: None,
+                error: None,
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            eprintln!("Savepoint operation failed: {}", e);
+            error_response(&e)
         }
     }
 }
 
-// Handler for POST /api/transactions/commit
-async fn commit_transaction_handler(
-    State(app_state): State<Arc<Mutex<ApiMgr>>>,
-    Json(payload): Json<TransactionActionRequest>,
+// Handler for GET /api/transactions/:transaction_id/savepoints
+async fn list_savepoints_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path(transaction_id): Path<u64>,
 ) -> impl IntoResponse {
-    println!("Received request to commit transaction ID: {}", payload.transaction_id);
-    let mut manager = match app_state.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => {
-            eprintln!("Failed to acquire lock for ApiMgr: {:?}", poisoned);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("Failed to acquire lock".to_string()),
-                }),
-            )
-                .into_response();
-        }
-    };
+    run_blocking(app_state, move |manager| match manager.savepoints(transaction_id) {
+        Ok(names) => (
+            StatusCode::OK,
+            Json(ApiResponse::<Vec<String>> {
+                success: true,
+                data: Some(names),
+                error: None,
+            }),
+        )
+            .into_response(),
+        Err(e) => error_response(&e),
+    })
+    .await
+}
 
-    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-        manager.commit_transaction(payload.transaction_id)
-    })) {
-        Ok(_) => {
-            println!("Transaction {} committed successfully", payload.transaction_id);
-            (
-                StatusCode::OK,
-                Json(ApiResponse::<()> {
+// Handler for POST /api/transactions/:transaction_id/savepoints
+async fn create_savepoint_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path(transaction_id): Path<u64>,
+    Json(payload): Json<CreateSavepointRequest>,
+) -> impl IntoResponse {
+    println!("Received request to create savepoint '{}' in transaction {}", payload.name, transaction_id);
+    run_blocking(app_state, move |manager| {
+        savepoint_response(manager.create_savepoint(transaction_id, payload.name))
+    })
+    .await
+}
+
+// Handler for POST /api/transactions/:transaction_id/savepoints/:name/rollback
+async fn rollback_to_savepoint_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path((transaction_id, name)): Path<(u64, String)>,
+) -> impl IntoResponse {
+    println!("Received request to roll back transaction {} to savepoint '{}'", transaction_id, name);
+    run_blocking(app_state, move |manager| {
+        savepoint_response(manager.rollback_to_savepoint(transaction_id, name))
+    })
+    .await
+}
+
+// Handler for DELETE /api/transactions/:transaction_id/savepoints/:name (释放保存点)
+async fn release_savepoint_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path((transaction_id, name)): Path<(u64, String)>,
+) -> impl IntoResponse {
+    println!("Received request to release savepoint '{}' in transaction {}", name, transaction_id);
+    run_blocking(app_state, move |manager| {
+        savepoint_response(manager.release_savepoint(transaction_id, name))
+    })
+    .await
+}
+
+// Handler for GET /api/snapshots
+async fn list_snapshots_handler(State(app_state): State<Arc<ApiMgr>>) -> impl IntoResponse {
+    run_blocking(app_state, move |manager| {
+        (
+            StatusCode::OK,
+            Json(ApiResponse {
+                success: true,
+                data: Some(manager.list_snapshots()),
+                error: None,
+            }),
+        )
+            .into_response()
+    })
+    .await
+}
+
+// Handler for POST /api/snapshots
+async fn create_snapshot_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Json(payload): Json<CreateSnapshotRequest>,
+) -> impl IntoResponse {
+    println!("Received request to create snapshot '{}'", payload.snapshot_id);
+    run_blocking(app_state, move |manager| {
+        match manager.create_snapshot(payload.snapshot_id) {
+            Ok(info) => (
+                StatusCode::CREATED,
+                Json(ApiResponse::<SnapshotInfo> {
                     success: true,
-                    data: None, // Or Some("Transaction committed".to_string())
+                    data: Some(info),
                     error: None,
                 }),
             )
-                .into_response()
-        }
-        Err(_) => {
-            eprintln!("Failed to commit transaction {} (panic occurred)", payload.transaction_id);
-            // 根据任务要求，如果 ApiMgr 方法尚未返回 Result，则可以继续依赖现有的 panic 行为。
-            // 理想情况下，ApiMgr::commit_transaction 会返回 Result<_, Error>
-            // 我们可以根据错误类型返回更具体的 HTTP 状态码和错误信息。
-            // 例如，如果事务 ID 无效，可以返回 StatusCode::BAD_REQUEST。
-            (
-                StatusCode::INTERNAL_SERVER_ERROR, // Or BAD_REQUEST if ID is known to be invalid
-                Json(ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Failed to commit transaction {}", payload.transaction_id)),
-                }),
-            )
-                .into_response()
+                .into_response(),
+            Err(e) => error_response(&e),
         }
-    }
+    })
+    .await
 }
 
-// Handler for POST /api/transactions/rollback
-async fn rollback_transaction_handler(
-    State(app_state): State<Arc<Mutex<ApiMgr>>>,
-    Json(payload): Json<TransactionActionRequest>,
+// Handler for POST /api/snapshots/:snapshot_id/restore
+async fn restore_snapshot_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path(snapshot_id): Path<String>,
 ) -> impl IntoResponse {
-    println!("Received request to rollback transaction ID: {}", payload.transaction_id);
-    let mut manager = match app_state.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => {
-            eprintln!("Failed to acquire lock for ApiMgr: {:?}", poisoned);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
+    println!("Received request to restore snapshot '{}'", snapshot_id);
+    run_blocking(app_state, move |manager| {
+        match manager.restore_snapshot(snapshot_id) {
+            Ok(()) => (
+                StatusCode::OK,
                 Json(ApiResponse::<()> {
-                    success: false,
+                    success: true,
                     data: None,
-                    error: Some("Failed to acquire lock".to_string()),
+                    error: None,
                 }),
             )
-                .into_response();
+                .into_response(),
+            Err(e) => error_response(&e),
         }
-    };
+    })
+    .await
+}
 
-    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-        manager.rollback_transaction(payload.transaction_id)
-    })) {
-        Ok(_) => {
-            println!("Transaction {} rollbacked successfully", payload.transaction_id);
-            (
+// Handler for DELETE /api/snapshots/:snapshot_id
+async fn delete_snapshot_handler(
+    State(app_state): State<Arc<ApiMgr>>,
+    Path(snapshot_id): Path<String>,
+) -> impl IntoResponse {
+    println!("Received request to delete snapshot '{}'", snapshot_id);
+    run_blocking(app_state, move |manager| {
+        match manager.delete_snapshot(snapshot_id) {
+            Ok(()) => (
                 StatusCode::OK,
                 Json(ApiResponse::<()> {
                     success: true,
-                    data: None, // Or Some("Transaction rollbacked".to_string())
-                    error: None,
-                }),
-            )
-                .into_response()
-        }
-        Err(_) => {
-            eprintln!("Failed to rollback transaction {} (panic occurred)", payload.transaction_id);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR, // Or BAD_REQUEST if ID is known to be invalid
-                Json(ApiResponse::<()> {
-                    success: false,
                     data: None,
-                    error: Some(format!("Failed to rollback transaction {}", payload.transaction_id)),
+                    error: None,
                 }),
             )
-                .into_response()
+                .into_response(),
+            Err(e) => error_response(&e),
         }
-    }
-}
\ No newline at end of file
+    })
+    .await
+}
+
+// 将 Error 映射为 HTTP 响应，error 字段为错误信息：
+// 请求参数格式错误或组合无效、已结束/未提交的事务和未来的时间戳为 400，在只读事务中写入为 403，
+// 事务、快照或保存点不存在为 404，写写冲突、可串行化验证失败或快照重名为 409 (客户端可以重试)，
+// 超时被回滚的事务和已被回收的历史版本为 410，条件写入的前提不成立为 412，批量操作过多为 413，
+// 值不适用于该操作为 422，读写数据文件或日志失败为 500
+fn error_response(err: &Error) -> axum::response::Response {
+    let status = match err {
+        Error::Parse(_)
+        | Error::InvalidArgument(_)
+        | Error::TxnNotActive(_)
+        | Error::TxnNotCommitted(_)
+        | Error::FutureTimestamp(_) => StatusCode::BAD_REQUEST,
+        Error::TxnReadOnly(_) => StatusCode::FORBIDDEN,
+        Error::NotFound(_) => StatusCode::NOT_FOUND,
+        Error::Conflict { .. } | Error::SerializationFailure { .. } | Error::AlreadyExists(_) => StatusCode::CONFLICT,
+        Error::TxnTimedOut(_) | Error::HistoryPruned(_) => StatusCode::GONE,
+        Error::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+        Error::BatchTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        Error::TypeMismatch { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        Error::Io { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        Json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(err.to_string()),
+        }),
+    )
+        .into_response()
+}